@@ -1,26 +1,19 @@
 use comfy_table::Table;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::io;
+use rustls::pki_types::CertificateDer;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
 use tokio::{sync::mpsc, time::Instant};
 use tokio_util::sync::CancellationToken;
 
 use crate::math;
 use crate::tls;
 
-fn render_stats_table(handshake_latencies: &mut [u128], tcp_connect_latencies: &mut [u128]) {
-    assert!(
-        !handshake_latencies.is_empty(),
-        "List of handshake latencies can not be empty"
-    );
-    assert!(
-        !tcp_connect_latencies.is_empty(),
-        "List of tcp connect latencies can not be empty"
-    );
-    handshake_latencies.sort();
-    tcp_connect_latencies.sort();
-    let mut table = Table::new();
-    let header = vec![
-        "Latencies",
+fn stats_header(name: &str) -> Vec<&str> {
+    vec![
+        name,
         "Min",
         "AVG",
         "50%’ile",
@@ -28,37 +21,445 @@ fn render_stats_table(handshake_latencies: &mut [u128], tcp_connect_latencies: &
         "99%’ile",
         "99.9%’ile",
         "Max",
-    ];
+    ]
+}
+
+fn stats_row(name: &str, values: &mut [u128], unit: &str) -> Vec<String> {
+    values.sort();
+    vec![
+        String::from(name),
+        format!("{}{unit}", values[0]),
+        format!("{}{unit}", math::avg(values)),
+        format!("{}{unit}", math::percentile(values, 50.0) as f32),
+        format!("{}{unit}", math::percentile(values, 95.0) as f32),
+        format!("{}{unit}", math::percentile(values, 99.0) as f32),
+        format!("{}{unit}", math::percentile(values, 99.9) as f32),
+        format!("{}{unit}", values.last().unwrap()),
+    ]
+}
+
+fn latency_row(name: &str, latencies: &mut [u128]) -> Vec<String> {
+    stats_row(name, latencies, "ms")
+}
+
+#[derive(Default)]
+struct Latencies {
+    handshake: Vec<u128>,
+    tcp_connect: Vec<u128>,
+    full_handshake: Vec<u128>,
+    resumed_handshake: Vec<u128>,
+    resumption_attempts: usize,
+    hello_retry_request: Vec<u128>,
+    early_data_accepted: Vec<u128>,
+    early_data_rejected: Vec<u128>,
+    time_to_first_byte: Vec<u128>,
+    server_flight: Vec<u128>,
+    finished_to_done: Vec<u128>,
+    rtt: Vec<u128>,
+    server_processing: Vec<u128>,
+    transfer: Vec<u128>,
+    transfer_throughput: Vec<u128>,
+    cert_verification: Vec<u128>,
+    signature_verification: Vec<u128>,
+    by_handshaker: BTreeMap<&'static str, Vec<u128>>,
+}
+
+impl Latencies {
+    fn record(&mut self, latencies: &tls::TlsDuration) {
+        let handshake = latencies.handshake.as_millis();
+        self.handshake.push(handshake);
+        self.tcp_connect.push(latencies.tcp_connect.as_millis());
+        self.by_handshaker
+            .entry(latencies.handshaker)
+            .or_default()
+            .push(handshake);
+        if latencies.resumption_attempted {
+            self.resumption_attempts += 1;
+        }
+        if latencies.resumed {
+            self.resumed_handshake.push(handshake);
+        } else {
+            self.full_handshake.push(handshake);
+        }
+        if latencies.hello_retry_request {
+            self.hello_retry_request.push(handshake);
+        }
+        let phases = &latencies.phases;
+        if let Some(time_to_first_byte) = phases.time_to_first_byte {
+            self.time_to_first_byte.push(time_to_first_byte.as_millis());
+        }
+        if let Some(server_flight) = phases.server_flight {
+            self.server_flight.push(server_flight.as_millis());
+        }
+        if let Some(finished_to_done) = phases.finished_to_done {
+            self.finished_to_done.push(finished_to_done.as_millis());
+        }
+        if let Some(cert_verification) = latencies.cert_verification {
+            self.cert_verification.push(cert_verification.as_micros());
+        }
+        if let Some(signature_verification) = latencies.signature_verification {
+            self.signature_verification
+                .push(signature_verification.as_micros());
+        }
+        if let Some(transfer) = latencies.transfer {
+            self.transfer.push(transfer.as_millis());
+            // Bytes per µs is MB/s, scaled to KB/s to keep slow transfers apart.
+            let micros = transfer.as_micros().max(1);
+            self.transfer_throughput
+                .push(latencies.transfer_bytes as u128 * 1000 / micros);
+        }
+        if let Some(rtt) = latencies.rtt {
+            self.rtt.push(rtt.as_millis());
+        }
+        if let Some(server_processing) = latencies.server_processing() {
+            self.server_processing.push(server_processing.as_millis());
+        }
+        match latencies.early_data_accepted {
+            Some(true) => self.early_data_accepted.push(handshake),
+            Some(false) => self.early_data_rejected.push(handshake),
+            None => {}
+        }
+    }
+}
+
+fn render_stats_table(latencies: &mut Latencies) {
+    assert!(
+        !latencies.handshake.is_empty(),
+        "List of handshake latencies can not be empty"
+    );
+    assert!(
+        !latencies.tcp_connect.is_empty(),
+        "List of tcp connect latencies can not be empty"
+    );
+    let mut table = Table::new();
     table
-        .set_header(header)
-        .add_row(vec![
-            String::from("TLS Handshake"),
-            format!("{}ms", handshake_latencies[0]),
-            format!("{}ms", math::avg(handshake_latencies)),
-            format!("{}ms", math::percentile(handshake_latencies, 50.0) as f32),
-            format!("{}ms", math::percentile(handshake_latencies, 95.0) as f32),
-            format!("{}ms", math::percentile(handshake_latencies, 99.0) as f32),
-            format!("{}ms", math::percentile(handshake_latencies, 99.9) as f32),
-            format!("{}ms", handshake_latencies.last().unwrap()),
-        ])
-        .add_row(vec![
-            String::from("TCP Connect"),
-            format!("{}ms", tcp_connect_latencies[0]),
-            format!("{}ms", math::avg(tcp_connect_latencies)),
-            format!("{}ms", math::percentile(tcp_connect_latencies, 50.0) as f32),
-            format!("{}ms", math::percentile(tcp_connect_latencies, 95.0) as f32),
-            format!("{}ms", math::percentile(tcp_connect_latencies, 99.0) as f32),
-            format!("{}ms", math::percentile(tcp_connect_latencies, 99.9) as f32),
-            format!("{}ms", tcp_connect_latencies.last().unwrap()),
-        ]);
+        .set_header(stats_header("Latencies"))
+        .add_row(latency_row("TLS Handshake", &mut latencies.handshake));
+    if latencies.by_handshaker.len() > 1 {
+        for (handshaker, handshakes) in latencies.by_handshaker.iter_mut() {
+            table.add_row(latency_row(
+                &format!("TLS Handshake ({handshaker})"),
+                handshakes,
+            ));
+        }
+    }
+    if !latencies.resumed_handshake.is_empty() {
+        if !latencies.full_handshake.is_empty() {
+            table.add_row(latency_row("Full Handshake", &mut latencies.full_handshake));
+        }
+        table.add_row(latency_row(
+            "Resumed Handshake",
+            &mut latencies.resumed_handshake,
+        ));
+    }
+    if !latencies.hello_retry_request.is_empty() {
+        table.add_row(latency_row(
+            "HelloRetryRequest",
+            &mut latencies.hello_retry_request,
+        ));
+    }
+    if !latencies.early_data_accepted.is_empty() {
+        table.add_row(latency_row(
+            "0-RTT Accepted",
+            &mut latencies.early_data_accepted,
+        ));
+    }
+    if !latencies.early_data_rejected.is_empty() {
+        table.add_row(latency_row(
+            "0-RTT Rejected",
+            &mut latencies.early_data_rejected,
+        ));
+    }
+    if !latencies.time_to_first_byte.is_empty() {
+        table.add_row(latency_row(
+            "ClientHello to first byte",
+            &mut latencies.time_to_first_byte,
+        ));
+    }
+    if !latencies.server_flight.is_empty() {
+        table.add_row(latency_row("Server flight", &mut latencies.server_flight));
+    }
+    if !latencies.finished_to_done.is_empty() {
+        table.add_row(latency_row(
+            "Finished to done",
+            &mut latencies.finished_to_done,
+        ));
+    }
+    if !latencies.cert_verification.is_empty() {
+        table.add_row(stats_row(
+            "Verify server certificate",
+            &mut latencies.cert_verification,
+            "µs",
+        ));
+    }
+    if !latencies.signature_verification.is_empty() {
+        table.add_row(stats_row(
+            "Verify handshake signature",
+            &mut latencies.signature_verification,
+            "µs",
+        ));
+    }
+    if !latencies.transfer.is_empty() {
+        table.add_row(latency_row("Data transfer", &mut latencies.transfer));
+        table.add_row(stats_row(
+            "Transfer throughput",
+            &mut latencies.transfer_throughput,
+            "KB/s",
+        ));
+    }
+    if !latencies.server_processing.is_empty() {
+        table.add_row(latency_row(
+            "Server processing (est.)",
+            &mut latencies.server_processing,
+        ));
+    }
+    if !latencies.rtt.is_empty() {
+        table.add_row(latency_row("Network RTT", &mut latencies.rtt));
+    }
+    table.add_row(latency_row("TCP Connect", &mut latencies.tcp_connect));
 
     println!("{table}");
 }
 
+/// Bytes, TCP reads and writes and retransmits each handshake took.
+#[derive(Default)]
+struct WireBytes {
+    bytes_sent: Vec<u128>,
+    bytes_received: Vec<u128>,
+    client_hello: Vec<u128>,
+    server_flight: Vec<u128>,
+    writes: Vec<u128>,
+    reads: Vec<u128>,
+    retransmits: Vec<u128>,
+    cert_bytes_saved: Vec<u128>,
+}
+
+impl WireBytes {
+    fn record(&mut self, data: &tls::TlsDuration) {
+        let wire = &data.wire;
+        self.bytes_sent.push(wire.bytes_sent as u128);
+        self.bytes_received.push(wire.bytes_received as u128);
+        self.client_hello.push(wire.client_hello as u128);
+        self.server_flight.push(wire.server_flight as u128);
+        self.writes.push(wire.writes as u128);
+        self.reads.push(wire.reads as u128);
+        if let Some(retransmits) = data.retransmits {
+            self.retransmits.push(retransmits as u128);
+        }
+        if let Some(compressed_certificate) = data.compressed_certificate {
+            self.cert_bytes_saved
+                .push(compressed_certificate.bytes_saved() as u128);
+        }
+    }
+
+    fn total_retransmits(&self) -> u128 {
+        self.retransmits.iter().sum()
+    }
+
+    fn render(&mut self) {
+        if self.bytes_sent.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table
+            .set_header(stats_header("Handshake I/O"))
+            .add_row(stats_row("Bytes sent", &mut self.bytes_sent, "B"))
+            .add_row(stats_row("Bytes received", &mut self.bytes_received, "B"))
+            .add_row(stats_row("ClientHello", &mut self.client_hello, "B"))
+            .add_row(stats_row("Server flight", &mut self.server_flight, "B"))
+            .add_row(stats_row("TCP writes", &mut self.writes, ""))
+            .add_row(stats_row("TCP reads", &mut self.reads, ""));
+        if !self.retransmits.is_empty() {
+            table.add_row(stats_row("TCP retransmits", &mut self.retransmits, ""));
+        }
+        if !self.cert_bytes_saved.is_empty() {
+            table.add_row(stats_row(
+                "Certificate bytes saved",
+                &mut self.cert_bytes_saved,
+                "B",
+            ));
+        }
+        println!("{table}");
+    }
+}
+
+/// Counts how many handshakes negotiated each value of a parameter.
+#[derive(Default)]
+struct Distribution(BTreeMap<String, u128>);
+
+impl Distribution {
+    fn add(&mut self, value: String) {
+        *self.0.entry(value).or_default() += 1;
+    }
+
+    /// Value, handshake count and share of each value.
+    fn rows(&self) -> Vec<Vec<String>> {
+        let total: u128 = self.0.values().sum();
+        self.0
+            .iter()
+            .map(|(value, count)| {
+                vec![
+                    value.clone(),
+                    count.to_string(),
+                    format!("{:.2}%", *count as f32 / total as f32 * 100.0),
+                ]
+            })
+            .collect()
+    }
+
+    fn render(&self, name: &str) {
+        if self.0.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table.set_header(vec![name, "Handshakes", "Share"]);
+        for row in self.rows() {
+            table.add_row(row);
+        }
+        println!("{table}");
+    }
+}
+
+/// Distributions of the parameters each handshake negotiated. A pool member
+/// configured differently than the others shows up as a small share.
+#[derive(Default)]
+struct NegotiatedParameters {
+    protocol_version: Distribution,
+    cipher_suite: Distribution,
+    kx_group: Distribution,
+    signature_scheme: Distribution,
+    alpn_protocol: Distribution,
+    resumed: Distribution,
+    server_authentication: Distribution,
+    cert_chain: Distribution,
+    cert_compression: Distribution,
+    ech_status: Distribution,
+}
+
+impl NegotiatedParameters {
+    fn record(&mut self, data: &tls::TlsDuration) {
+        let label = |value: Option<String>| value.unwrap_or_else(|| String::from("none"));
+        self.protocol_version
+            .add(label(data.protocol_version.map(|v| format!("{v:?}"))));
+        self.cipher_suite
+            .add(label(data.cipher_suite.map(|suite| format!("{suite:?}"))));
+        self.kx_group
+            .add(label(data.kx_group.map(|group| format!("{group:?}"))));
+        self.signature_scheme.add(label(
+            data.signature_scheme.map(|scheme| format!("{scheme:?}")),
+        ));
+        self.alpn_protocol
+            .add(tls::alpn_label(data.alpn_protocol.as_deref()));
+        self.resumed
+            .add(String::from(if data.resumed { "yes" } else { "no" }));
+        self.server_authentication
+            .add(String::from(if data.raw_public_key {
+                "Raw public key"
+            } else {
+                "X.509 certificate"
+            }));
+        self.cert_chain.add(format!(
+            "{} certificate{}, {} bytes",
+            data.cert_chain_len,
+            if data.cert_chain_len == 1 { "" } else { "s" },
+            data.cert_chain_size
+        ));
+        self.cert_compression.add(label(
+            data.compressed_certificate
+                .map(|compressed| format!("{:?}", compressed.algorithm)),
+        ));
+        // Only handshakes configured with ECH report a status, others skip the row.
+        if let Some(status) = data.ech_status {
+            self.ech_status.add(format!("{status:?}"));
+        }
+    }
+
+    fn render(&self) {
+        if self.resumed.0.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table.set_header(vec!["Negotiated parameter", "Value", "Handshakes", "Share"]);
+        let parameters = [
+            ("Protocol version", &self.protocol_version),
+            ("Cipher suite", &self.cipher_suite),
+            ("Key exchange group", &self.kx_group),
+            ("Signature scheme", &self.signature_scheme),
+            ("ALPN protocol", &self.alpn_protocol),
+            ("Resumed", &self.resumed),
+            ("Server authentication", &self.server_authentication),
+            ("Certificate chain", &self.cert_chain),
+            ("Certificate compression", &self.cert_compression),
+            ("Encrypted Client Hello", &self.ech_status),
+        ];
+        for (name, distribution) in parameters {
+            for (i, mut row) in distribution.rows().into_iter().enumerate() {
+                row.insert(0, String::from(if i == 0 { name } else { "" }));
+                table.add_row(row);
+            }
+        }
+        println!("{table}");
+    }
+}
+
+/// What the benchmark was configured with, for the summary line.
+pub struct Report {
+    pub resumption: bool,
+    pub zero_rtt: bool,
+    /// Crypto provider backing rustls, if rustls performed handshakes
+    pub crypto_provider: Option<&'static str>,
+    pub fips: bool,
+}
+
+/// Prints the details of every distinct certificate servers presented, most
+/// seen first, so different certificates behind one endpoint stand out.
+fn render_server_certificates(certificates: HashMap<CertificateDer<'static>, u128>) {
+    let total: u128 = certificates.values().sum();
+    let mut certificates: Vec<_> = certificates.into_iter().collect();
+    certificates.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    for (cert, count) in certificates {
+        let mut table = Table::new();
+        let share = format!("{count} ({:.2}%)", count as f32 / total as f32 * 100.0);
+        match tls::certificate::parse_certificate(&cert) {
+            Ok(info) => {
+                let expiry = if info.days_to_expiry < 0 {
+                    format!(
+                        "{} (expired {} days ago)",
+                        info.not_after, -info.days_to_expiry
+                    )
+                } else {
+                    format!("{} (in {} days)", info.not_after, info.days_to_expiry)
+                };
+                table
+                    .set_header(vec![String::from("Server certificate"), info.subject])
+                    .add_row(vec![String::from("Handshakes"), share])
+                    .add_row(vec![
+                        String::from("Key"),
+                        format!("{} {} bits", info.key_algorithm, info.key_size),
+                    ])
+                    .add_row(vec![String::from("Signature"), info.signature_algorithm])
+                    .add_row(vec![
+                        String::from("Subject alt names"),
+                        info.subject_alt_names.join(", "),
+                    ])
+                    .add_row(vec![String::from("Expires"), expiry]);
+            }
+            Err(error) => {
+                table
+                    .set_header(vec![String::from("Server certificate"), error.to_string()])
+                    .add_row(vec![String::from("Handshakes"), share]);
+            }
+        }
+        println!("{table}");
+    }
+}
+
 pub fn show_progress_and_stats(
     duration: u64,
     ramp_up_sec: u64,
     concurrently: usize,
+    report: Report,
     mut rx: mpsc::UnboundedReceiver<Result<tls::TlsDuration, io::Error>>,
     token: CancellationToken,
 ) {
@@ -70,8 +471,11 @@ pub fn show_progress_and_stats(
 
     let mut err_count: u128 = 0;
     let mut handshakes_count: u128 = 0;
-    let mut handshake_latencies: Vec<u128> = Vec::new();
-    let mut tcp_connect_latencies: Vec<u128> = Vec::new();
+    let mut latencies = Latencies::default();
+    let mut negotiated = NegotiatedParameters::default();
+    let mut wire_bytes = WireBytes::default();
+    let mut server_certificates: HashMap<CertificateDer<'static>, u128> = HashMap::new();
+    let mut errors = Distribution::default();
     let mut ramp_up_reset_done = false;
 
     let mut throughput = 0;
@@ -95,7 +499,8 @@ pub fn show_progress_and_stats(
             handshakes_count, err_count, throughput, elapsed_secs
         ));
 
-        if data.is_err() {
+        if let Err(error) = &data {
+            errors.add(tls::error_class(error));
             err_count += 1;
             continue;
         }
@@ -114,22 +519,176 @@ pub fn show_progress_and_stats(
             ramp_up_reset_done = true;
         }
 
-        let latencies = data.unwrap();
-        handshake_latencies.push(latencies.handshake.as_millis());
-        tcp_connect_latencies.push(latencies.tcp_connect.as_millis());
+        let data = data.unwrap();
+        latencies.record(&data);
+        negotiated.record(&data);
+        wire_bytes.record(&data);
+        if let Some(cert) = data.end_entity_cert.filter(|_| !data.raw_public_key) {
+            *server_certificates.entry(cert).or_default() += 1;
+        }
     }
 
     if ramp_up_sec > 0 && duration == 0 {
         elapsed_secs += ramp_up_sec as f32;
     }
 
-    spinner.finish_with_message(format!(
+    let mut summary = format!(
         "TLS handshakes: {} | errors: {} | throughput {} h/s | duration {:.2}s | success ratio {}%",
         handshakes_count,
         err_count,
         throughput,
         elapsed_secs,
         handshakes_count as f32 / (err_count + handshakes_count) as f32 * 100.0
-    ));
-    render_stats_table(&mut handshake_latencies, &mut tcp_connect_latencies);
+    );
+    if report.resumption && latencies.resumption_attempts > 0 {
+        summary.push_str(&format!(
+            " | resumption hit ratio {}%",
+            latencies.resumed_handshake.len() as f32 / latencies.resumption_attempts as f32 * 100.0
+        ));
+    }
+    if !latencies.hello_retry_request.is_empty() {
+        summary.push_str(&format!(
+            " | HelloRetryRequests: {}",
+            latencies.hello_retry_request.len()
+        ));
+    }
+    if wire_bytes.total_retransmits() > 0 {
+        summary.push_str(&format!(
+            " | TCP retransmits: {}",
+            wire_bytes.total_retransmits()
+        ));
+    }
+    if report.zero_rtt {
+        summary.push_str(&format!(
+            " | 0-RTT accepted: {} | 0-RTT rejected: {}",
+            latencies.early_data_accepted.len(),
+            latencies.early_data_rejected.len()
+        ));
+    }
+    if let Some(crypto_provider) = report.crypto_provider {
+        summary.push_str(&format!(" | crypto provider {crypto_provider}"));
+    }
+    if report.fips {
+        summary.push_str(" | FIPS mode active");
+    }
+    spinner.finish_with_message(summary);
+    if !latencies.handshake.is_empty() {
+        render_stats_table(&mut latencies);
+    }
+    wire_bytes.render();
+    negotiated.render();
+    render_server_certificates(server_certificates);
+    errors.render("Error");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::{CipherSuite, NamedGroup, ProtocolVersion};
+    use tokio::time::Duration;
+
+    fn tls_duration(
+        protocol_version: Option<ProtocolVersion>,
+        cipher_suite: Option<CipherSuite>,
+        kx_group: Option<NamedGroup>,
+    ) -> tls::TlsDuration {
+        tls::TlsDuration {
+            handshaker: "rustls",
+            tcp_connect: Duration::from_millis(1),
+            handshake: Duration::from_millis(2),
+            resumption_attempted: false,
+            resumed: false,
+            hello_retry_request: false,
+            early_data_accepted: None,
+            protocol_version,
+            cipher_suite,
+            kx_group,
+            signature_scheme: None,
+            alpn_protocol: None,
+            cert_chain_len: 1,
+            cert_chain_size: 512,
+            end_entity_cert: None,
+            raw_public_key: false,
+            compressed_certificate: None,
+            ech_status: None,
+            wire: Default::default(),
+            phases: Default::default(),
+            cert_verification: None,
+            signature_verification: None,
+            transfer: None,
+            transfer_bytes: 0,
+            rtt: None,
+            retransmits: None,
+        }
+    }
+
+    #[test]
+    fn test_distribution_rows() {
+        let mut distribution = Distribution::default();
+        assert!(distribution.rows().is_empty());
+        for value in ["b", "a", "b", "b"] {
+            distribution.add(String::from(value));
+        }
+        assert_eq!(
+            distribution.rows(),
+            vec![vec!["a", "1", "25.00%"], vec!["b", "3", "75.00%"]]
+        );
+    }
+
+    #[test]
+    fn test_negotiated_parameters() {
+        let mut negotiated = NegotiatedParameters::default();
+        let tls13 = tls_duration(
+            Some(ProtocolVersion::TLSv1_3),
+            Some(CipherSuite::TLS13_AES_128_GCM_SHA256),
+            Some(NamedGroup::X25519),
+        );
+        negotiated.record(&tls13);
+        negotiated.record(&tls13);
+        negotiated.record(&tls_duration(
+            Some(ProtocolVersion::TLSv1_2),
+            Some(CipherSuite::TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384),
+            Some(NamedGroup::secp256r1),
+        ));
+        negotiated.record(&tls_duration(
+            Some(ProtocolVersion::Unknown(0x0305)),
+            Some(CipherSuite::Unknown(0x1234)),
+            None,
+        ));
+
+        assert_eq!(
+            negotiated.protocol_version.rows(),
+            vec![
+                vec!["ProtocolVersion(0x305)", "1", "25.00%"],
+                vec!["TLSv1_2", "1", "25.00%"],
+                vec!["TLSv1_3", "2", "50.00%"],
+            ]
+        );
+        assert_eq!(
+            negotiated.cipher_suite.rows(),
+            vec![
+                vec!["CipherSuite(0x1234)", "1", "25.00%"],
+                vec!["TLS13_AES_128_GCM_SHA256", "2", "50.00%"],
+                vec!["TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384", "1", "25.00%"],
+            ]
+        );
+        assert_eq!(
+            negotiated.kx_group.rows(),
+            vec![
+                vec!["X25519", "2", "50.00%"],
+                vec!["none", "1", "25.00%"],
+                vec!["secp256r1", "1", "25.00%"],
+            ]
+        );
+        assert_eq!(
+            negotiated.signature_scheme.rows(),
+            vec![vec!["none", "4", "100.00%"]]
+        );
+        assert_eq!(negotiated.resumed.rows(), vec![vec!["no", "4", "100.00%"]]);
+        assert_eq!(
+            negotiated.cert_chain.rows(),
+            vec![vec!["1 certificate, 512 bytes", "4", "100.00%"]]
+        );
+        assert!(negotiated.ech_status.rows().is_empty());
+    }
 }
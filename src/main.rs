@@ -1,6 +1,19 @@
 use clap::Parser;
 
-use std::{io, net::SocketAddr, sync::Arc, thread::available_parallelism};
+use rustls::client::{
+    danger::ServerCertVerifier, ClientSessionMemoryCache, ClientSessionStore, ResolvesClientCert,
+    Resumption, Tls12Resumption,
+};
+use std::{
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::available_parallelism,
+};
 use tokio::{net, sync::mpsc, task};
 use tokio_util::sync::CancellationToken;
 
@@ -17,18 +30,26 @@ struct Cli {
     #[arg(short, long)]
     endpoint: String,
 
+    /// Server name sent as SNI, defaults to the host of the endpoint when it is a DNS name
+    #[arg(long)]
+    sni: Option<String>,
+
     /// Protocol to use when running TLS benchmark
     #[arg(short, value_enum, default_value_t = Protocol::Tcp)]
     protocol: Protocol,
 
-    /// TLS version number
+    /// TLS version number, TLS 1.0 and 1.1 require the openssl handshaker
     #[arg(short, value_enum, default_value_t = TlsVersion::Tls12)]
     tls_version: TlsVersion,
 
-    /// TLS Zero RTT boolean
+    /// TLS Zero RTT boolean, sends early data on resumed TLS 1.3 handshakes
     #[arg(short, long, default_value_t = false)]
     zero_rtt: bool,
 
+    /// Early data payload sent with Zero RTT, supports \r \n and \t escapes
+    #[arg(long, default_value = "GET / HTTP/1.1\\r\\n\\r\\n")]
+    early_data: String,
+
     /// Duration of benchamrk test in seconds
     #[arg(short, long, default_value_t = 0)]
     duration: u64,
@@ -48,6 +69,103 @@ struct Cli {
     /// Ramp up seconds, eatch step up per second is calculated = max_handshakes_per_second * elapsed_seconds / ramp_up_sec
     #[arg(short, long, default_value_t = 0)]
     ramp_up_sec: u64,
+
+    /// TLS session resumption mode, session-id applies to TLS 1.2 only as TLS 1.3 always resumes with tickets
+    #[arg(long, value_enum, default_value_t = ResumptionMode::None)]
+    resumption: ResumptionMode,
+
+    /// Percentage of handshakes that attempt resumption in mixed resumption mode
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u8).range(0..=100))]
+    resumption_ratio: u8,
+
+    /// Server certificate verification, disabled by default
+    #[arg(long, value_enum, default_value_t = VerifyMode::None)]
+    verify: VerifyMode,
+
+    /// PEM encoded CA bundle used with --verify ca-file
+    #[arg(long, required_if_eq("verify", "ca-file"))]
+    ca_file: Option<PathBuf>,
+
+    /// Comma separated TLS 1.2 and TLS 1.3 cipher suites to offer, defaults to all supported
+    #[arg(long, value_delimiter = ',')]
+    ciphers: Vec<String>,
+
+    /// Comma separated key exchange groups to offer, a key share is sent for the first one
+    #[arg(long, value_delimiter = ',')]
+    groups: Vec<String>,
+
+    /// Key exchange group the initial TLS 1.3 key share is sent for, forces a HelloRetryRequest from servers preferring another offered group
+    #[arg(long)]
+    hrr_key_share: Option<String>,
+
+    /// Comma separated ALPN protocols to offer, e.g. h2,http/1.1
+    #[arg(long, value_delimiter = ',')]
+    alpn: Vec<String>,
+
+    /// Fail handshakes negotiating another ALPN protocol, none expects no ALPN
+    #[arg(long)]
+    expect_alpn: Option<String>,
+
+    /// PEM encoded client certificate chain for mutual TLS
+    #[arg(long, requires = "client_key", conflicts_with = "client_cert_dir")]
+    client_cert: Option<PathBuf>,
+
+    /// PEM encoded private key of the client certificate
+    #[arg(long, requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    /// Directory of <name>.crt and <name>.key pairs rotated across handshakes for mutual TLS
+    #[arg(long)]
+    client_cert_dir: Option<PathBuf>,
+
+    /// How client identities from --client-cert-dir are picked
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ClientCertRotation::RoundRobin,
+        requires = "client_cert_dir"
+    )]
+    client_cert_rotation: ClientCertRotation,
+
+    /// Crypto library backing rustls, defaults to aws-lc-rs when compiled in
+    #[arg(long, value_enum)]
+    crypto_provider: Option<CryptoProviderKind>,
+
+    /// Comma separated TLS client implementations handshakes alternate between
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "rustls")]
+    handshaker: Vec<HandshakerKind>,
+
+    /// Certificate compression algorithm offered to servers, TLS 1.3 only
+    #[arg(long, value_enum, default_value_t = CertCompression::None)]
+    cert_compression: CertCompression,
+
+    /// Base64 encoded ECH config list to encrypt the ClientHello with, TLS 1.3 and aws-lc-rs only
+    #[arg(long, conflicts_with = "ech_config_file")]
+    ech_config: Option<String>,
+
+    /// File with the ECH config list to encrypt the ClientHello with, PEM or binary
+    #[arg(long)]
+    ech_config_file: Option<PathBuf>,
+
+    /// Comma separated signature schemes to offer, steers servers with several certificates, e.g. ECDSA_NISTP256_SHA256
+    #[arg(long, value_delimiter = ',')]
+    sig_schemes: Vec<String>,
+
+    /// Largest TLS record the client sends in bytes, header included
+    #[arg(long, value_parser = clap::value_parser!(u16).range(32..=16389))]
+    max_fragment_size: Option<u16>,
+
+    /// Bytes of application data sent after each handshake, the transfer counts towards the timeout
+    #[arg(long, default_value_t = 0)]
+    transfer_bytes: usize,
+
+    /// PEM encoded public key the server authenticates with instead of a certificate, RFC 7250 raw public keys need TLS 1.3
+    #[arg(long, conflicts_with = "verify")]
+    server_public_key: Option<PathBuf>,
+
+    /// PEM or DER encoded CRL server certificates are checked against, repeatable, requires --verify
+    #[arg(long)]
+    crl: Vec<PathBuf>,
 }
 
 #[derive(clap::ValueEnum, Clone)]
@@ -58,35 +176,281 @@ enum Protocol {
 
 #[derive(clap::ValueEnum, Clone)]
 enum TlsVersion {
+    Tls10,
+    Tls11,
     Tls12,
     Tls13,
 }
 
+#[derive(clap::ValueEnum, Clone)]
+enum VerifyMode {
+    None,
+    Webpki,
+    System,
+    CaFile,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+enum HandshakerKind {
+    Rustls,
+    Openssl,
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum CryptoProviderKind {
+    AwsLcRs,
+    Ring,
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum ClientCertRotation {
+    RoundRobin,
+    Random,
+}
+
+#[derive(clap::ValueEnum, Clone, PartialEq)]
+enum CertCompression {
+    None,
+    Zlib,
+    Brotli,
+}
+
+#[derive(clap::ValueEnum, Clone)]
+enum ResumptionMode {
+    None,
+    SessionId,
+    Ticket,
+    Mixed,
+}
+
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let cli = Cli::parse();
 
-    let mut tls_config = tls::tls_config(Some(cli.zero_rtt), Some(&[&rustls::version::TLS12]));
-    if let TlsVersion::Tls13 = cli.tls_version {
-        tls_config = tls::tls_config(Some(cli.zero_rtt), Some(&[&rustls::version::TLS13]));
+    if cli.zero_rtt && !matches!(cli.tls_version, TlsVersion::Tls13) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Zero RTT requires TLS 1.3, use -t tls13",
+        ));
+    }
+
+    if cli.hrr_key_share.is_some() && !matches!(cli.tls_version, TlsVersion::Tls13) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "HelloRetryRequest requires TLS 1.3, use -t tls13",
+        ));
+    }
+
+    let ech_config_list = match (&cli.ech_config, &cli.ech_config_file) {
+        (Some(encoded), _) => Some(tls::ech::decode_ech_config_list(encoded)?),
+        (None, Some(path)) => Some(tls::ech::load_ech_config_list(path)?),
+        (None, None) => None,
+    };
+    if ech_config_list.is_some() && !matches!(cli.tls_version, TlsVersion::Tls13) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ECH requires TLS 1.3, use -t tls13",
+        ));
+    }
+
+    // A remembered key exchange group would stop HelloRetryRequests after the first handshake.
+    let mut session_store: Arc<dyn ClientSessionStore> =
+        Arc::new(ClientSessionMemoryCache::new(256));
+    if cli.hrr_key_share.is_some() {
+        session_store = Arc::new(tls::session::NoKxHintStore::new(session_store));
+    }
+
+    let (resumption, resumption_ratio) = match cli.resumption {
+        ResumptionMode::None if cli.zero_rtt => (Resumption::store(session_store), 100),
+        ResumptionMode::None => (Resumption::disabled(), 0),
+        ResumptionMode::SessionId => (
+            Resumption::store(session_store).tls12_resumption(Tls12Resumption::SessionIdOnly),
+            100,
+        ),
+        ResumptionMode::Ticket => (Resumption::store(session_store), 100),
+        ResumptionMode::Mixed => (Resumption::store(session_store), cli.resumption_ratio),
+    };
+
+    let provider_kind = match cli.crypto_provider {
+        Some(CryptoProviderKind::AwsLcRs) => tls::provider::ProviderKind::AwsLcRs,
+        Some(CryptoProviderKind::Ring) => tls::provider::ProviderKind::Ring,
+        None => tls::provider::ProviderKind::default(),
+    };
+    let mut crypto_provider = provider_kind.provider()?;
+    let ech_mode = ech_config_list
+        .map(|config_list| tls::ech::ech_mode(provider_kind, config_list))
+        .transpose()?;
+    if !cli.ciphers.is_empty() {
+        crypto_provider = tls::provider::with_cipher_suites(crypto_provider, &cli.ciphers)?;
+    }
+    if !cli.groups.is_empty() {
+        crypto_provider =
+            tls::provider::with_kx_groups(crypto_provider, provider_kind, &cli.groups)?;
+    }
+    if let Some(hrr_key_share) = &cli.hrr_key_share {
+        crypto_provider =
+            tls::provider::with_initial_key_share(crypto_provider, provider_kind, hrr_key_share)?;
+    }
+
+    let roots = match cli.verify {
+        VerifyMode::None => None,
+        VerifyMode::Webpki => Some(tls::verify::webpki_roots()),
+        VerifyMode::System => Some(tls::verify::system_roots()?),
+        VerifyMode::CaFile => Some(tls::verify::ca_file_roots(cli.ca_file.as_deref().unwrap())?),
+    };
+    let mut verifier: Option<Arc<dyn ServerCertVerifier>> = None;
+    if let Some(roots) = roots {
+        let mut crls = Vec::new();
+        for path in &cli.crl {
+            crls.extend(tls::verify::load_crls(path)?);
+        }
+        verifier = Some(tls::verify::webpki_verifier(
+            roots,
+            crypto_provider.clone(),
+            crls,
+        )?);
+    } else if !cli.crl.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "CRLs are only checked with certificate verification, use --verify",
+        ));
+    }
+    if let Some(public_key) = &cli.server_public_key {
+        if !matches!(cli.tls_version, TlsVersion::Tls13) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Raw public keys require TLS 1.3, use -t tls13",
+            ));
+        }
+        verifier = Some(Arc::new(tls::verify::RawPublicKeyVerifier::from_pem_file(
+            public_key,
+            crypto_provider.clone(),
+        )?));
+    }
+
+    let mut signature_schemes = None;
+    if !cli.sig_schemes.is_empty() {
+        signature_schemes = Some(tls::verify::signature_schemes(
+            &crypto_provider,
+            &cli.sig_schemes,
+        )?);
+    }
+
+    let mut client_auth: Option<Arc<dyn ResolvesClientCert>> = None;
+    if let (Some(cert_file), Some(key_file)) = (&cli.client_cert, &cli.client_key) {
+        let identity = tls::identity::load_identity(cert_file, key_file, &crypto_provider)?;
+        client_auth = Some(Arc::new(rustls::sign::SingleCertAndKey::from(identity)));
+    }
+    if let Some(cert_dir) = &cli.client_cert_dir {
+        let identities = tls::identity::load_identity_dir(cert_dir, &crypto_provider)?;
+        let rotation = match cli.client_cert_rotation {
+            ClientCertRotation::RoundRobin => tls::identity::Rotation::RoundRobin,
+            ClientCertRotation::Random => tls::identity::Rotation::Random,
+        };
+        client_auth = Some(Arc::new(tls::identity::IdentityPool::new(
+            identities, rotation,
+        )));
+    }
+
+    let alpn_protocols: Vec<Vec<u8>> = cli.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+
+    let cert_decompressors = tls::compression::cert_decompressors(match cli.cert_compression {
+        CertCompression::None => None,
+        CertCompression::Zlib => Some(rustls::CertificateCompressionAlgorithm::Zlib),
+        CertCompression::Brotli => Some(rustls::CertificateCompressionAlgorithm::Brotli),
+    });
+
+    let mut early_data: Option<Arc<[u8]>> = None;
+    if cli.zero_rtt {
+        early_data = Some(tls::early_data_payload(&cli.early_data).into());
+    }
+
+    let mut handshakers: Vec<Arc<dyn tls::Handshaker>> = Vec::new();
+    for kind in unique_handshakers(&cli.handshaker) {
+        match kind {
+            HandshakerKind::Rustls => {
+                let tls_versions: &[&rustls::SupportedProtocolVersion] = match cli.tls_version {
+                    TlsVersion::Tls10 | TlsVersion::Tls11 => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "TLS 1.0 and 1.1 are not supported by rustls, use --handshaker openssl",
+                        ));
+                    }
+                    TlsVersion::Tls12 => &[&rustls::version::TLS12],
+                    TlsVersion::Tls13 => &[&rustls::version::TLS13],
+                };
+                let tls_options = tls::TlsOptions {
+                    zero_rtt: cli.zero_rtt,
+                    tls_versions: Some(tls_versions.to_vec()),
+                    resumption: None,
+                    verifier: verifier.clone(),
+                    crypto_provider: Some(crypto_provider.clone()),
+                    alpn_protocols: alpn_protocols.clone(),
+                    client_auth: client_auth.clone(),
+                    cert_decompressors: cert_decompressors.clone(),
+                    ech_mode: ech_mode.clone(),
+                    signature_schemes: signature_schemes.clone(),
+                    max_fragment_size: cli.max_fragment_size.map(usize::from),
+                };
+                let full_tls_config = Arc::new(tls::tls_config(tls_options.clone())?);
+                let resumption_tls_config = Arc::new(tls::tls_config(tls::TlsOptions {
+                    resumption: Some(resumption.clone()),
+                    ..tls_options
+                })?);
+                handshakers.push(Arc::new(tls::RustlsHandshaker {
+                    full_tls_config,
+                    resumption_tls_config,
+                    resumption_ratio,
+                    early_data: early_data.clone(),
+                    transfer_bytes: cli.transfer_bytes,
+                }));
+            }
+            HandshakerKind::Openssl => {
+                handshakers.push(openssl_handshaker(&cli, resumption_ratio)?)
+            }
+        }
     }
+    let handshake_counter = Arc::new(AtomicUsize::new(0));
 
     let mut is_smtp = false;
     if let Protocol::Smtp = cli.protocol {
         is_smtp = true;
     }
 
+    let server_name = tls::server_name(&cli.endpoint, cli.sni.as_deref())?;
     let endpoint: SocketAddr = net::lookup_host(cli.endpoint).await?.next().unwrap();
+    let target = Arc::new(tls::Target {
+        endpoint,
+        server_name,
+        timeout_ms: cli.timeout_ms,
+        is_smtp,
+        expected_alpn: cli.expect_alpn,
+    });
+
+    if cli.zero_rtt || !matches!(cli.verify, VerifyMode::None) || cli.server_public_key.is_some() {
+        for handshaker in &handshakers {
+            tls::preflight_handshake(&target, handshaker.as_ref()).await?;
+        }
+    }
 
     let (tx, rx) = mpsc::unbounded_channel::<Result<tls::TlsDuration, std::io::Error>>();
     let token = CancellationToken::new();
     let cancel_token = token.clone();
+    // The crypto provider, FIPS included, only backs the rustls handshaker.
+    let rustls_handshaker = cli.handshaker.contains(&HandshakerKind::Rustls);
+    let report = cli::Report {
+        resumption: resumption_ratio > 0,
+        zero_rtt: cli.zero_rtt,
+        crypto_provider: rustls_handshaker.then_some(provider_kind.name()),
+        fips: rustls_handshaker && crypto_provider.fips(),
+    };
     let mut tasks = task::JoinSet::new();
     tasks.spawn_blocking(move || {
         cli::show_progress_and_stats(
             cli.duration,
             cli.ramp_up_sec,
             cli.concurrently,
+            report,
             rx,
             cancel_token,
         )
@@ -97,7 +461,9 @@ async fn main() -> io::Result<()> {
     );
 
     for _ in 0..cli.concurrently {
-        let local_tls_config = tls_config.clone();
+        let local_handshakers = handshakers.clone();
+        let local_handshake_counter = handshake_counter.clone();
+        let local_target = target.clone();
         let local_token = token.clone();
         let tx_result = tx.clone();
         let local_traffic_controller = traffic_controller.clone();
@@ -108,7 +474,9 @@ async fn main() -> io::Result<()> {
                         break;
                     },
                     _ = local_traffic_controller.acquire() => {
-                        tls::tls_handshaker(endpoint, cli.timeout_ms, is_smtp, local_tls_config.clone(), tx_result.clone()).await;
+                        let n = local_handshake_counter.fetch_add(1, Ordering::Relaxed);
+                        let handshaker = &local_handshakers[n % local_handshakers.len()];
+                        tls::tls_handshaker(&local_target, handshaker.as_ref(), n / local_handshakers.len(), tx_result.clone()).await;
                     }
                 }
             }
@@ -124,8 +492,96 @@ async fn main() -> io::Result<()> {
     Ok(())
 }
 
+/// Drops repeated handshakers keeping the order they were given in, so
+/// handshakes alternate evenly between the distinct implementations.
+fn unique_handshakers(kinds: &[HandshakerKind]) -> Vec<HandshakerKind> {
+    let mut unique = Vec::with_capacity(kinds.len());
+    for kind in kinds {
+        if !unique.contains(kind) {
+            unique.push(kind.clone());
+        }
+    }
+    unique
+}
+
+/// Builds the OpenSSL handshaker, rejecting the options only rustls implements.
+#[cfg(feature = "openssl")]
+fn openssl_handshaker(cli: &Cli, resumption_ratio: u8) -> io::Result<Arc<dyn tls::Handshaker>> {
+    use openssl::ssl::SslVersion;
+
+    let unsupported = [
+        ("--zero-rtt", cli.zero_rtt),
+        ("--ciphers", !cli.ciphers.is_empty()),
+        ("--sig-schemes", !cli.sig_schemes.is_empty()),
+        ("--max-fragment-size", cli.max_fragment_size.is_some()),
+        ("--server-public-key", cli.server_public_key.is_some()),
+        ("--crl", !cli.crl.is_empty()),
+        ("--transfer-bytes", cli.transfer_bytes > 0),
+        ("--groups", !cli.groups.is_empty()),
+        ("--hrr-key-share", cli.hrr_key_share.is_some()),
+        ("--client-cert-dir", cli.client_cert_dir.is_some()),
+        ("--verify webpki", matches!(cli.verify, VerifyMode::Webpki)),
+        (
+            "--cert-compression",
+            cli.cert_compression != CertCompression::None,
+        ),
+        (
+            "--ech-config",
+            cli.ech_config.is_some() || cli.ech_config_file.is_some(),
+        ),
+    ];
+    if let Some((option, _)) = unsupported.iter().find(|(_, used)| *used) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{option} is not supported by the openssl handshaker"),
+        ));
+    }
+
+    let version = match cli.tls_version {
+        TlsVersion::Tls10 => SslVersion::TLS1,
+        TlsVersion::Tls11 => SslVersion::TLS1_1,
+        TlsVersion::Tls12 => SslVersion::TLS1_2,
+        TlsVersion::Tls13 => SslVersion::TLS1_3,
+    };
+    let roots = match cli.verify {
+        VerifyMode::System => Some(tls::openssl::Roots::System),
+        VerifyMode::CaFile => Some(tls::openssl::Roots::CaFile(cli.ca_file.as_deref().unwrap())),
+        VerifyMode::None | VerifyMode::Webpki => None,
+    };
+    let client_identity = cli.client_cert.as_deref().zip(cli.client_key.as_deref());
+    Ok(Arc::new(tls::openssl::OpensslHandshaker::new(
+        version,
+        roots,
+        &cli.alpn,
+        client_identity,
+        resumption_ratio,
+        matches!(cli.resumption, ResumptionMode::SessionId),
+    )?))
+}
+
+#[cfg(not(feature = "openssl"))]
+fn openssl_handshaker(_cli: &Cli, _resumption_ratio: u8) -> io::Result<Arc<dyn tls::Handshaker>> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "Handshaker openssl is not compiled in, rebuild with --features openssl",
+    ))
+}
+
 #[test]
 fn verify_cli() {
     use clap::CommandFactory;
     Cli::command().debug_assert();
 }
+
+#[test]
+fn test_unique_handshakers() {
+    use HandshakerKind::{Openssl, Rustls};
+    assert_eq!(
+        unique_handshakers(&[Rustls, Openssl, Rustls]),
+        [Rustls, Openssl]
+    );
+    assert_eq!(
+        unique_handshakers(&[Openssl, Openssl, Rustls]),
+        [Openssl, Rustls]
+    );
+}
@@ -1,65 +1,285 @@
-mod verify;
+pub mod certificate;
+pub mod compression;
+pub mod ech;
+pub mod identity;
+#[cfg(feature = "openssl")]
+pub mod openssl;
+pub mod probe;
+pub mod provider;
+pub mod session;
+pub mod tcp_info;
+pub mod verify;
+pub mod wire;
 
-use rustls::{crypto::aws_lc_rs as provider, pki_types::ServerName, SupportedProtocolVersion};
+use rustls::{
+    client::{danger::ServerCertVerifier, EchMode, EchStatus, ResolvesClientCert, Resumption},
+    compress::CertDecompressor,
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, ServerName},
+    AlertDescription, CertificateError, CipherSuite, HandshakeKind, NamedGroup, PeerIncompatible,
+    ProtocolVersion, SignatureScheme, SupportedProtocolVersion,
+};
 use std::{
+    future::Future,
     io::{Error, ErrorKind},
     net::{IpAddr, SocketAddr},
+    pin::Pin,
     sync::Arc,
 };
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::TcpStream,
     sync::mpsc,
     time::{timeout, Duration, Instant},
 };
-use tokio_rustls::{
-    rustls::{ClientConfig, RootCertStore},
-    TlsConnector,
-};
+use tokio_rustls::{client::TlsStream, rustls::ClientConfig, TlsConnector};
+use wire::{CountingStream, HandshakePhases, WireStats};
+
+/// Where and how every handshake of a benchmark run connects.
+#[derive(Clone, Debug)]
+pub struct Target {
+    pub endpoint: SocketAddr,
+    pub server_name: ServerName<'static>,
+    pub timeout_ms: u64,
+    pub is_smtp: bool,
+    /// Handshakes negotiating another ALPN protocol fail, `none` expects no ALPN
+    pub expected_alpn: Option<String>,
+}
+
+/// Picks the server name sent as SNI, an explicit `sni` wins over the host
+/// part of `endpoint`. IP addresses are used as is, so no SNI is sent for them.
+pub fn server_name(endpoint: &str, sni: Option<&str>) -> Result<ServerName<'static>, Error> {
+    let host = match sni {
+        Some(sni) => sni,
+        None => endpoint
+            .rsplit_once(':')
+            .map_or(endpoint, |(host, _)| host)
+            .trim_start_matches('[')
+            .trim_end_matches(']'),
+    };
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(ServerName::from(ip));
+    }
+
+    ServerName::try_from(host.to_string()).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid server name {host}"),
+        )
+    })
+}
 
 #[derive(Debug)]
 pub struct TlsDuration {
+    /// Name of the handshaker that performed the handshake
+    pub handshaker: &'static str,
     pub tcp_connect: Duration,
     pub handshake: Duration,
+    /// Set when the handshake offered a stored session, if there was one
+    pub resumption_attempted: bool,
+    pub resumed: bool,
+    pub hello_retry_request: bool,
+    /// Set when early data was sent with 0-RTT, true if the server accepted it
+    pub early_data_accepted: Option<bool>,
+    pub protocol_version: Option<ProtocolVersion>,
+    pub cipher_suite: Option<CipherSuite>,
+    pub kx_group: Option<NamedGroup>,
+    /// Scheme the server signed the handshake with, rustls only
+    pub signature_scheme: Option<SignatureScheme>,
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// Number of certificates in the chain the server presented
+    pub cert_chain_len: usize,
+    /// Size of the DER encoded certificates in the chain, in bytes
+    pub cert_chain_size: usize,
+    /// Certificate the server authenticated with, first in its chain, or its
+    /// public key when it authenticated with a raw public key
+    pub end_entity_cert: Option<CertificateDer<'static>>,
+    pub raw_public_key: bool,
+    /// Set when the server compressed its certificate
+    pub compressed_certificate: Option<compression::CompressedCertificate>,
+    /// Whether Encrypted Client Hello was offered and accepted, rustls only
+    pub ech_status: Option<EchStatus>,
+    pub wire: WireStats,
+    pub phases: HandshakePhases,
+    /// Client side time spent verifying the server certificate, rustls only
+    pub cert_verification: Option<Duration>,
+    /// Client side time spent verifying the server's handshake signature, rustls only
+    pub signature_verification: Option<Duration>,
+    /// Time to send application data after the handshake until the server
+    /// answered our close_notify, set when a transfer was requested
+    pub transfer: Option<Duration>,
+    /// Application data bytes sent in the transfer
+    pub transfer_bytes: usize,
+    /// Smoothed RTT the kernel measured once connected, Linux only
+    pub rtt: Option<Duration>,
+    /// TCP segments retransmitted until the handshake was done, Linux only
+    pub retransmits: Option<u32>,
+}
+
+impl TlsDuration {
+    /// Handshake latency left after the network round trips it needed, an
+    /// estimate of the time the server spent computing.
+    pub fn server_processing(&self) -> Option<Duration> {
+        let round_trips = tcp_info::round_trips(
+            self.protocol_version?,
+            self.resumed,
+            self.hello_retry_request,
+        );
+        Some(tcp_info::server_processing(
+            self.handshake,
+            self.rtt?,
+            round_trips,
+        ))
+    }
 }
 
-pub fn tls_config(
-    zero_rtt: Option<bool>,
-    supported_tls_version: Option<&[&'static SupportedProtocolVersion]>,
-) -> ClientConfig {
-    let mut root_cert_store = RootCertStore::empty();
-    root_cert_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+/// Name of a negotiated ALPN protocol as shown in reports, `none` if the
+/// server did not select one.
+pub fn alpn_label(alpn_protocol: Option<&[u8]>) -> String {
+    alpn_protocol.map_or(String::from("none"), |protocol| {
+        String::from_utf8_lossy(protocol).into_owned()
+    })
+}
 
-    let mut config = ClientConfig::builder_with_protocol_versions(
-        supported_tls_version.unwrap_or(rustls::ALL_VERSIONS),
-    )
-    .with_root_certificates(root_cert_store)
-    .with_no_client_auth();
+/// Turns `\r`, `\n` and `\t` escape sequences given on the command line into
+/// the characters they stand for.
+pub fn early_data_payload(payload: &str) -> Vec<u8> {
+    payload
+        .replace("\\r", "\r")
+        .replace("\\n", "\n")
+        .replace("\\t", "\t")
+        .into_bytes()
+}
 
-    config
-        .dangerous()
-        .set_certificate_verifier(Arc::new(verify::NoCertificateVerification::new(
-            provider::default_provider(),
-        )));
+/// Groups a failed handshake into an error class for the report. Certificate
+/// alerts sent by the server concern our client certificate, so they get their
+/// own class to tell client authentication failures apart.
+pub fn error_class(error: &Error) -> String {
+    match error
+        .get_ref()
+        .and_then(|error| error.downcast_ref::<rustls::Error>())
+    {
+        Some(rustls::Error::AlertReceived(
+            alert @ (AlertDescription::CertificateRequired
+            | AlertDescription::BadCertificate
+            | AlertDescription::UnsupportedCertificate
+            | AlertDescription::CertificateRevoked
+            | AlertDescription::CertificateExpired
+            | AlertDescription::CertificateUnknown
+            | AlertDescription::UnknownCA
+            | AlertDescription::AccessDenied),
+        )) => format!("Client auth alert: {alert:?}"),
+        Some(rustls::Error::AlertReceived(alert)) => format!("Alert received: {alert:?}"),
+        Some(rustls::Error::PeerIncompatible(
+            PeerIncompatible::ServerRejectedEncryptedClientHello(retry_configs),
+        )) => match retry_configs {
+            Some(_) => String::from("ECH rejected, server sent retry configs"),
+            None => String::from("ECH rejected"),
+        },
+        Some(rustls::Error::InvalidCertificate(CertificateError::Revoked)) => {
+            String::from("Revoked certificate")
+        }
+        Some(rustls::Error::InvalidCertificate(error)) => {
+            format!("Invalid server certificate: {error:?}")
+        }
+        Some(error) => format!("TLS error: {error}"),
+        None if error.kind() == ErrorKind::TimedOut => String::from("Timeout"),
+        None => error.to_string(),
+    }
+}
 
-    config.enable_early_data = zero_rtt.unwrap_or(false);
-    config
+/// Decides if the n-th handshake should attempt session resumption so that
+/// `ratio` percent of all handshakes are spread evenly over the run.
+pub fn attempt_resumption(n: usize, ratio: u8) -> bool {
+    let ratio = ratio.min(100) as usize;
+    (n % 100) * ratio % 100 < ratio
 }
 
-async fn handshake(
-    host: IpAddr,
-    port: u16,
-    is_smtp: bool,
-    tls_config: ClientConfig,
-) -> Result<TlsDuration, Error> {
-    let tcp_now = Instant::now();
-    let mut stream = TcpStream::connect((host, port)).await?;
-    if is_smtp {
+/// Client settings a rustls configuration is built from, unset fields fall back to defaults.
+#[derive(Clone, Debug, Default)]
+pub struct TlsOptions {
+    pub zero_rtt: bool,
+    pub tls_versions: Option<Vec<&'static SupportedProtocolVersion>>,
+    pub resumption: Option<Resumption>,
+    pub verifier: Option<Arc<dyn ServerCertVerifier>>,
+    pub crypto_provider: Option<CryptoProvider>,
+    pub alpn_protocols: Vec<Vec<u8>>,
+    pub client_auth: Option<Arc<dyn ResolvesClientCert>>,
+    pub cert_decompressors: Vec<&'static dyn CertDecompressor>,
+    pub ech_mode: Option<EchMode>,
+    pub signature_schemes: Option<Vec<SignatureScheme>>,
+    pub max_fragment_size: Option<usize>,
+}
+
+pub fn tls_config(options: TlsOptions) -> Result<ClientConfig, Error> {
+    let TlsOptions {
+        zero_rtt,
+        tls_versions,
+        resumption,
+        verifier,
+        crypto_provider,
+        alpn_protocols,
+        client_auth,
+        cert_decompressors,
+        ech_mode,
+        signature_schemes,
+        max_fragment_size,
+    } = options;
+    let crypto_provider = Arc::new(crypto_provider.unwrap_or_else(provider::default_provider));
+    let verifier = verifier.unwrap_or_else(|| {
+        Arc::new(verify::NoCertificateVerification::new(
+            crypto_provider.as_ref().clone(),
+        ))
+    });
+    let verifier = Arc::new(verify::InstrumentedVerifier::new(
+        verifier,
+        signature_schemes,
+    ));
+
+    let builder = ClientConfig::builder_with_provider(crypto_provider);
+    // ECH is only defined for TLS 1.3, so it takes the place of the protocol versions.
+    let builder = match ech_mode {
+        Some(ech_mode) => builder.with_ech(ech_mode),
+        None => {
+            builder.with_protocol_versions(tls_versions.as_deref().unwrap_or(rustls::ALL_VERSIONS))
+        }
+    }
+    .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?
+    .dangerous()
+    .with_custom_certificate_verifier(verifier);
+    let mut config = match client_auth {
+        Some(client_auth) => builder.with_client_cert_resolver(client_auth),
+        None => builder.with_no_client_auth(),
+    };
+
+    #[cfg(feature = "fips")]
+    if !config.fips() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "TLS configuration is not FIPS approved",
+        ));
+    }
+
+    config.enable_early_data = zero_rtt;
+    config.resumption = resumption.unwrap_or_else(Resumption::disabled);
+    config.alpn_protocols = alpn_protocols;
+    config.max_fragment_size = max_fragment_size;
+    config.cert_decompressors = cert_decompressors;
+    // Client certificates are sent uncompressed, keeping their cost out of mTLS latencies.
+    config.cert_compressors = Vec::new();
+    Ok(config)
+}
+
+/// Opens the TCP connection a handshake runs over, issuing STARTTLS first for SMTP.
+async fn connect(target: &Target) -> Result<TcpStream, Error> {
+    let mut stream = TcpStream::connect(target.endpoint).await?;
+    if target.is_smtp {
         let mut buffer = vec![0; 1024];
         stream.read_buf(&mut buffer).await?;
 
         stream
-            .write_all(&format!("EHLO {}\r\n", host).into_bytes())
+            .write_all(&format!("EHLO {}\r\n", target.endpoint.ip()).into_bytes())
             .await?;
         stream.flush().await?;
         stream.read_buf(&mut buffer).await?;
@@ -77,71 +297,444 @@ async fn handshake(
             ));
         }
     }
-    let tcp_connect_duration = tcp_now.elapsed();
+    Ok(stream)
+}
+
+/// Fails the handshake if the negotiated ALPN protocol isn't the expected one.
+fn check_alpn(target: &Target, alpn_protocol: Option<&[u8]>) -> Result<(), Error> {
+    if let Some(expected_alpn) = &target.expected_alpn {
+        let alpn = alpn_label(alpn_protocol);
+        if &alpn != expected_alpn {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Negotiated ALPN protocol {alpn} does not match expected {expected_alpn}"),
+            ));
+        }
+    }
+    Ok(())
+}
 
-    let domain = ServerName::from(host);
+async fn handshake(
+    target: &Target,
+    tls_config: Arc<ClientConfig>,
+    resumption: bool,
+    early_data: Option<&[u8]>,
+    transfer_bytes: usize,
+) -> Result<(TlsDuration, TlsStream<CountingStream<TcpStream>>), Error> {
+    let tcp_now = Instant::now();
+    let stream = connect(target).await?;
+    let tcp_connect_duration = tcp_now.elapsed();
+    let rtt = tcp_info::tcp_info(&stream).map(|info| info.rtt);
+    let stream = CountingStream::new(stream);
 
-    let tls_connector = TlsConnector::from(Arc::new(tls_config));
+    let tls_connector = TlsConnector::from(tls_config).early_data(early_data.is_some());
     let handshake_now = Instant::now();
-    let mut tls_stream = tls_connector.connect(domain.to_owned(), stream).await?;
-    let handshake_duration = handshake_now.elapsed();
+    let mut tls_stream = tls_connector
+        .connect(target.server_name.clone(), stream)
+        .await?;
+
+    // With a resumable ticket the connector hands back the stream before the
+    // handshake is done, flushing the early data then completes the handshake.
+    let mut early_data_accepted = None;
+    if tls_stream.get_ref().1.is_handshaking() {
+        tls_stream.write_all(early_data.unwrap_or_default()).await?;
+        tls_stream.flush().await?;
+        early_data_accepted = Some(tls_stream.get_ref().1.is_early_data_accepted());
+    }
+    let handshake_done = Instant::now();
+    let handshake_duration = handshake_done - handshake_now;
+    let wire = tls_stream.get_ref().0.stats();
+    let phases = tls_stream.get_ref().0.phases(handshake_done);
+    let retransmits =
+        tcp_info::tcp_info(tls_stream.get_ref().0.get_ref()).map(|info| info.retransmits);
 
+    if let (None, Some(early_data)) = (early_data_accepted, early_data) {
+        tls_stream.write_all(early_data).await?;
+        tls_stream.flush().await?;
+    }
+
+    let transfer = match transfer_bytes {
+        0 => {
+            tls_stream.shutdown().await?;
+            None
+        }
+        transfer_bytes => Some(transfer(&mut tls_stream, transfer_bytes).await?),
+    };
+
+    let connection = tls_stream.get_ref().1;
+    let resumed = connection.handshake_kind() == Some(HandshakeKind::Resumed);
+    let hello_retry_request =
+        connection.handshake_kind() == Some(HandshakeKind::FullWithHelloRetryRequest);
+    let cipher_suite = connection
+        .negotiated_cipher_suite()
+        .map(|suite| suite.suite());
+    let kx_group = connection
+        .negotiated_key_exchange_group()
+        .map(|group| group.name());
+    let alpn_protocol = connection.alpn_protocol().map(|protocol| protocol.to_vec());
+    let cert_chain = connection.peer_certificates().unwrap_or_default();
+    let probe = probe::take();
+
+    check_alpn(target, alpn_protocol.as_deref())?;
+
+    Ok((
+        TlsDuration {
+            handshaker: "rustls",
+            tcp_connect: tcp_connect_duration,
+            handshake: handshake_duration,
+            resumption_attempted: resumption,
+            resumed,
+            hello_retry_request,
+            early_data_accepted,
+            protocol_version: connection.protocol_version(),
+            cipher_suite,
+            kx_group,
+            signature_scheme: probe.signature_scheme,
+            alpn_protocol,
+            cert_chain_len: cert_chain.len(),
+            cert_chain_size: cert_chain.iter().map(|cert| cert.len()).sum(),
+            end_entity_cert: cert_chain.first().cloned(),
+            raw_public_key: probe.raw_public_key,
+            compressed_certificate: probe.compressed_certificate,
+            ech_status: Some(connection.ech_status())
+                .filter(|status| *status != EchStatus::NotOffered),
+            wire,
+            phases,
+            cert_verification: probe.cert_verification,
+            signature_verification: probe.signature_verification,
+            transfer,
+            transfer_bytes,
+            rtt,
+            retransmits,
+        },
+        tls_stream,
+    ))
+}
+
+/// Sends `bytes` of zeros as application data, split into records of at most
+/// the configured fragment size, then closes the connection. The server read
+/// all of it once it answers our close_notify, which ends the transfer.
+async fn transfer<S: AsyncRead + AsyncWrite + Unpin>(
+    tls_stream: &mut S,
+    bytes: usize,
+) -> Result<Duration, Error> {
+    let transfer_now = Instant::now();
+    let chunk = [0; 16 * 1024];
+    let mut remaining = bytes;
+    while remaining > 0 {
+        let len = remaining.min(chunk.len());
+        tls_stream.write_all(&chunk[..len]).await?;
+        remaining -= len;
+    }
     tls_stream.shutdown().await?;
+    read_until_close(tls_stream).await?;
+    Ok(transfer_now.elapsed())
+}
 
-    Ok(TlsDuration {
-        tcp_connect: tcp_connect_duration,
-        handshake: handshake_duration,
-    })
+/// Reads until the server closes the connection, processing any handshake
+/// messages and alerts it sends after the handshake. A server closing without
+/// close_notify is not treated as an error.
+async fn read_until_close<S: AsyncRead + Unpin>(tls_stream: &mut S) -> Result<(), Error> {
+    let mut buffer = vec![0; 1024];
+    loop {
+        match tls_stream.read(&mut buffer).await {
+            Ok(0) => return Ok(()),
+            Ok(_) => continue,
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Waits for the server's answer to our close_notify, as a TLS 1.3 server
+/// rejects the client certificate after the client already considers the
+/// handshake done. Only an alert, picked out of the read errors by `alert`,
+/// fails the handshake. A server keeping the connection open until the
+/// timeout, or dropping it, does not.
+async fn client_auth_verdict<S: AsyncRead + Unpin>(
+    target: &Target,
+    tls_stream: &mut S,
+    alert: fn(Error) -> Option<Error>,
+) -> Result<(), Error> {
+    match timeout(
+        Duration::from_millis(target.timeout_ms),
+        read_until_close(tls_stream),
+    )
+    .await
+    {
+        Ok(Err(error)) => alert(error).map_or(Ok(()), Err),
+        _ => Ok(()),
+    }
+}
+
+/// TLS 1.3 session tickets are sent by the server after the handshake, so
+/// they are read until the server closes the connection or the timeout hits.
+async fn receive_session_tickets<S: AsyncRead + Unpin>(target: &Target, tls_stream: &mut S) {
+    let _ = timeout(
+        Duration::from_millis(target.timeout_ms),
+        read_until_close(tls_stream),
+    )
+    .await;
+}
+
+/// Keeps read errors carrying an alert the server sent.
+fn rustls_alert(error: Error) -> Option<Error> {
+    let alert = error
+        .get_ref()
+        .and_then(|error| error.downcast_ref::<rustls::Error>())
+        .is_some_and(|error| matches!(error, rustls::Error::AlertReceived(_)));
+    alert.then_some(error)
 }
 
 async fn handshake_with_timeout(
-    host: IpAddr,
-    port: u16,
-    is_smtp: bool,
-    tls_config: ClientConfig,
-    timeout_ms: u64,
+    target: &Target,
+    tls_config: Arc<ClientConfig>,
+    resumption: bool,
+    early_data: Option<&[u8]>,
+    transfer_bytes: usize,
 ) -> Result<TlsDuration, Error> {
+    let client_auth = tls_config.client_auth_cert_resolver.has_certs();
     let handshake_timeout = timeout(
-        Duration::from_millis(timeout_ms),
-        handshake(host, port, is_smtp, tls_config),
+        Duration::from_millis(target.timeout_ms),
+        handshake(target, tls_config, resumption, early_data, transfer_bytes),
     );
-    handshake_timeout.await?
+    let (latencies, mut tls_stream) = probe::scope(handshake_timeout).await??;
+
+    if client_auth && tls_stream.get_ref().1.protocol_version() == Some(ProtocolVersion::TLSv1_3) {
+        client_auth_verdict(target, &mut tls_stream, rustls_alert).await?;
+    }
+
+    if resumption && tls_stream.get_ref().1.protocol_version() == Some(ProtocolVersion::TLSv1_3) {
+        receive_session_tickets(target, &mut tls_stream).await;
+    }
+
+    Ok(latencies)
+}
+
+/// A single handshake in flight, timeout included.
+pub type HandshakeFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TlsDuration, Error>> + Send + 'a>>;
+
+/// TLS client implementation the benchmark performs handshakes with.
+pub trait Handshaker: Send + Sync {
+    /// Performs the n-th handshake of this handshaker against the target.
+    fn handshake<'a>(&'a self, target: &'a Target, n: usize) -> HandshakeFuture<'a>;
+}
+
+/// Handshakes with rustls, resuming sessions for the given share of handshakes.
+pub struct RustlsHandshaker {
+    pub full_tls_config: Arc<ClientConfig>,
+    pub resumption_tls_config: Arc<ClientConfig>,
+    pub resumption_ratio: u8,
+    pub early_data: Option<Arc<[u8]>>,
+    /// Bytes of application data sent after each handshake, none if zero
+    pub transfer_bytes: usize,
+}
+
+impl Handshaker for RustlsHandshaker {
+    fn handshake<'a>(&'a self, target: &'a Target, n: usize) -> HandshakeFuture<'a> {
+        let resumption = attempt_resumption(n, self.resumption_ratio);
+        let tls_config = if resumption {
+            self.resumption_tls_config.clone()
+        } else {
+            self.full_tls_config.clone()
+        };
+        Box::pin(handshake_with_timeout(
+            target,
+            tls_config,
+            resumption,
+            self.early_data.as_deref(),
+            self.transfer_bytes,
+        ))
+    }
 }
 
 pub async fn tls_handshaker(
-    endpoint: SocketAddr,
-    timeout_ms: u64,
-    is_smtp: bool,
-    tls_config: ClientConfig,
+    target: &Target,
+    handshaker: &dyn Handshaker,
+    n: usize,
     tx_result: mpsc::UnboundedSender<Result<TlsDuration, Error>>,
 ) {
-    let result = handshake_with_timeout(
-        endpoint.ip(),
-        endpoint.port(),
-        is_smtp,
-        tls_config,
-        timeout_ms,
-    )
-    .await;
+    let result = handshaker.handshake(target, n).await;
 
     let _ = tx_result.send(result);
 }
 
+/// Runs a single handshake before the benchmark starts, so certificate errors
+/// fail the run up front and the session store holds a ticket for 0-RTT.
+pub async fn preflight_handshake(
+    target: &Target,
+    handshaker: &dyn Handshaker,
+) -> Result<(), Error> {
+    handshaker.handshake(target, 0).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_tls_config_zero_rtt() {
-        let config = tls_config(Some(true), Some(&[&rustls::version::TLS12]));
+        let config = tls_config(TlsOptions {
+            zero_rtt: true,
+            tls_versions: Some(vec![&rustls::version::TLS12]),
+            ..Default::default()
+        })
+        .unwrap();
         assert!(config.enable_early_data);
     }
 
+    #[test]
+    fn test_tls_config_no_usable_cipher_suites() {
+        let names = vec![String::from("TLS13_AES_128_GCM_SHA256")];
+        let crypto_provider =
+            provider::with_cipher_suites(provider::default_provider(), &names).unwrap();
+        let config = tls_config(TlsOptions {
+            tls_versions: Some(vec![&rustls::version::TLS12]),
+            crypto_provider: Some(crypto_provider),
+            ..Default::default()
+        });
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_attempt_resumption() {
+        let attempts = |ratio| (0..1000).filter(|n| attempt_resumption(*n, ratio)).count();
+        assert_eq!(attempts(0), 0);
+        assert_eq!(attempts(30), 300);
+        assert_eq!(attempts(50), 500);
+        assert_eq!(attempts(100), 1000);
+        assert!(attempt_resumption(0, 50));
+        assert!(!attempt_resumption(1, 50));
+    }
+
+    #[test]
+    fn test_early_data_payload() {
+        assert_eq!(
+            early_data_payload("GET / HTTP/1.1\\r\\n\\r\\n"),
+            b"GET / HTTP/1.1\r\n\r\n"
+        );
+        assert_eq!(early_data_payload("ping"), b"ping");
+    }
+
+    #[test]
+    fn test_error_class() {
+        let alert = Error::new(
+            ErrorKind::InvalidData,
+            rustls::Error::AlertReceived(AlertDescription::CertificateRequired),
+        );
+        assert_eq!(
+            error_class(&alert),
+            "Client auth alert: CertificateRequired"
+        );
+
+        let alert = Error::new(
+            ErrorKind::InvalidData,
+            rustls::Error::AlertReceived(AlertDescription::HandshakeFailure),
+        );
+        assert_eq!(error_class(&alert), "Alert received: HandshakeFailure");
+
+        let ech = Error::new(
+            ErrorKind::InvalidData,
+            rustls::Error::PeerIncompatible(PeerIncompatible::ServerRejectedEncryptedClientHello(
+                None,
+            )),
+        );
+        assert_eq!(error_class(&ech), "ECH rejected");
+
+        let revoked = Error::new(
+            ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(CertificateError::Revoked),
+        );
+        assert_eq!(error_class(&revoked), "Revoked certificate");
+
+        let timeout = Error::new(ErrorKind::TimedOut, "deadline has elapsed");
+        assert_eq!(error_class(&timeout), "Timeout");
+    }
+
+    #[test]
+    fn test_alpn_label() {
+        assert_eq!(alpn_label(Some(b"h2")), "h2");
+        assert_eq!(alpn_label(None), "none");
+    }
+
+    #[test]
+    fn test_server_name() {
+        let name = |endpoint, sni| server_name(endpoint, sni).unwrap().to_str().into_owned();
+        assert_eq!(name("example.com:443", None), "example.com");
+        assert_eq!(
+            name("example.com:443", Some("tenant.example.com")),
+            "tenant.example.com"
+        );
+        assert_eq!(
+            name("127.0.0.1:443", Some("tenant.example.com")),
+            "tenant.example.com"
+        );
+        assert!(matches!(
+            server_name("127.0.0.1:443", None).unwrap(),
+            ServerName::IpAddress(_)
+        ));
+        assert!(matches!(
+            server_name("[::1]:443", None).unwrap(),
+            ServerName::IpAddress(_)
+        ));
+        assert!(server_name("127.0.0.1:443", Some("not a name")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_auth_verdict() {
+        let target = Target {
+            endpoint: "127.0.0.1:8000".parse().unwrap(),
+            server_name: server_name("127.0.0.1:8000", None).unwrap(),
+            timeout_ms: 10,
+            is_smtp: false,
+            expected_alpn: None,
+        };
+        // A server keeping the connection open doesn't fail the handshake.
+        let (mut client, _server) = tokio::io::duplex(1024);
+        assert!(client_auth_verdict(&target, &mut client, rustls_alert)
+            .await
+            .is_ok());
+
+        let alert = Error::new(
+            ErrorKind::InvalidData,
+            rustls::Error::AlertReceived(AlertDescription::UnknownCA),
+        );
+        assert!(rustls_alert(alert).is_some());
+        let reset = Error::new(ErrorKind::ConnectionReset, "connection reset");
+        assert!(rustls_alert(reset).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transfer() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let server = tokio::spawn(async move {
+            let mut received = Vec::new();
+            server.read_to_end(&mut received).await.unwrap();
+            received.len()
+        });
+        transfer(&mut client, 40_000).await.unwrap();
+        assert_eq!(server.await.unwrap(), 40_000);
+    }
+
     #[tokio::test]
     async fn test_handshake_connection_refused() {
-        let config = tls_config(Some(false), Some(&[&rustls::version::TLS12]));
-        let result =
-            handshake_with_timeout("127.0.0.1".parse().unwrap(), 8000, false, config, 10).await;
+        let config = Arc::new(
+            tls_config(TlsOptions {
+                tls_versions: Some(vec![&rustls::version::TLS12]),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let target = Target {
+            endpoint: "127.0.0.1:8000".parse().unwrap(),
+            server_name: server_name("127.0.0.1:8000", None).unwrap(),
+            timeout_ms: 10,
+            is_smtp: false,
+            expected_alpn: None,
+        };
+        let result = handshake_with_timeout(&target, config, false, None, 0).await;
         assert!(result.is_err());
         assert!(&result
             .err()
@@ -149,4 +742,79 @@ mod tests {
             .to_string()
             .contains("Connection refused"));
     }
+
+    /// Serves TLS 1.3 with 0-RTT enabled on a local port, reading each
+    /// connection until the client closes it.
+    async fn early_data_server() -> SocketAddr {
+        use rustls::pki_types::{pem::PemObject, PrivateKeyDer};
+
+        let cert_chain = CertificateDer::pem_file_iter("tests/fixtures/client.crt")
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_file("tests/fixtures/client.key").unwrap();
+        let mut config =
+            rustls::ServerConfig::builder_with_provider(Arc::new(provider::default_provider()))
+                .with_protocol_versions(&[&rustls::version::TLS13])
+                .unwrap()
+                .with_no_client_auth()
+                .with_single_cert(cert_chain, key)
+                .unwrap();
+        config.max_early_data_size = 1024;
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let mut tls_stream = acceptor.accept(stream).await?;
+                    tls_stream.read_to_end(&mut Vec::new()).await?;
+                    tls_stream.shutdown().await
+                });
+            }
+        });
+        endpoint
+    }
+
+    #[tokio::test]
+    async fn test_handshake_resumed_with_early_data() {
+        let endpoint = early_data_server().await;
+        let options = TlsOptions {
+            zero_rtt: true,
+            tls_versions: Some(vec![&rustls::version::TLS13]),
+            ..Default::default()
+        };
+        let handshaker = RustlsHandshaker {
+            full_tls_config: Arc::new(tls_config(options.clone()).unwrap()),
+            resumption_tls_config: Arc::new(
+                tls_config(TlsOptions {
+                    resumption: Some(Resumption::in_memory_sessions(256)),
+                    ..options
+                })
+                .unwrap(),
+            ),
+            resumption_ratio: 100,
+            early_data: Some(early_data_payload("GET / HTTP/1.1\\r\\n\\r\\n").into()),
+            transfer_bytes: 0,
+        };
+        let target = Target {
+            endpoint,
+            server_name: server_name(&endpoint.to_string(), None).unwrap(),
+            timeout_ms: 1000,
+            is_smtp: false,
+            expected_alpn: None,
+        };
+
+        let first = handshaker.handshake(&target, 0).await.unwrap();
+        assert!(first.resumption_attempted);
+        assert!(!first.resumed);
+        assert_eq!(first.early_data_accepted, None);
+
+        let second = handshaker.handshake(&target, 1).await.unwrap();
+        assert!(second.resumption_attempted);
+        assert!(second.resumed);
+        assert_eq!(second.early_data_accepted, Some(true));
+    }
 }
@@ -0,0 +1,103 @@
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::extensions::GeneralName;
+use x509_parser::objects::{oid2sn, oid_registry};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// Details of the certificate a server presented, as shown in the report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    /// Public key algorithm, with the curve for EC keys
+    pub key_algorithm: String,
+    pub key_size: usize,
+    pub signature_algorithm: String,
+    pub subject_alt_names: Vec<String>,
+    pub not_after: String,
+    /// Days until the certificate expires, negative once it has expired
+    pub days_to_expiry: i64,
+}
+
+fn oid_name(oid: &Oid) -> String {
+    oid2sn(oid, oid_registry()).map_or_else(|_| oid.to_id_string(), String::from)
+}
+
+fn general_name(name: &GeneralName) -> String {
+    match name {
+        GeneralName::DNSName(dns) => dns.to_string(),
+        GeneralName::IPAddress(&[a, b, c, d]) => IpAddr::from([a, b, c, d]).to_string(),
+        GeneralName::IPAddress(ip) => <[u8; 16]>::try_from(*ip)
+            .map_or_else(|_| name.to_string(), |ip| IpAddr::from(ip).to_string()),
+        name => name.to_string(),
+    }
+}
+
+/// Parses a DER encoded end-entity certificate.
+pub fn parse_certificate(der: &[u8]) -> Result<CertificateInfo, Error> {
+    let (_, cert) =
+        X509Certificate::from_der(der).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    let public_key = cert.public_key();
+    let mut key_algorithm = oid_name(&public_key.algorithm.algorithm);
+    if let Some(curve) = public_key
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|parameters| parameters.as_oid().ok())
+        .filter(|curve| !curve.as_bytes().is_empty())
+    {
+        key_algorithm = format!("{key_algorithm} {}", oid_name(&curve));
+    }
+    // Keys x509-parser doesn't know, like Ed25519, are sized by their encoding.
+    let key_size = match public_key.parsed().map(|key| key.key_size()) {
+        Ok(size) if size > 0 => size,
+        _ => public_key.subject_public_key.data.len() * 8,
+    };
+
+    let subject_alt_names = match cert.subject_alternative_name() {
+        Ok(Some(extension)) => extension
+            .value
+            .general_names
+            .iter()
+            .map(general_name)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let not_after = cert.validity().not_after;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+
+    Ok(CertificateInfo {
+        subject: cert.subject().to_string(),
+        key_algorithm,
+        key_size,
+        signature_algorithm: oid_name(&cert.signature_algorithm.algorithm),
+        subject_alt_names,
+        not_after: not_after.to_string(),
+        days_to_expiry: (not_after.timestamp() - now).div_euclid(24 * 60 * 60),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::{pem::PemObject, CertificateDer};
+
+    #[test]
+    fn test_parse_certificate() {
+        let cert = CertificateDer::from_pem_file("tests/fixtures/client.crt").unwrap();
+        let info = parse_certificate(&cert).unwrap();
+        assert_eq!(info.subject, "CN=tls-bench-client");
+        assert_eq!(info.key_algorithm, "id-ecPublicKey prime256v1");
+        assert_eq!(info.key_size, 256);
+        assert_eq!(info.signature_algorithm, "ecdsa-with-SHA256");
+        assert!(info.subject_alt_names.is_empty());
+        assert!(info.days_to_expiry > 0);
+
+        assert!(parse_certificate(b"not a certificate").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use rustls::compress::{self, CertDecompressor, DecompressionFailed};
+use rustls::CertificateCompressionAlgorithm;
+
+use super::probe;
+
+/// Certificate message a server sent compressed (RFC 8879).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressedCertificate {
+    pub algorithm: CertificateCompressionAlgorithm,
+    pub compressed_size: usize,
+    pub uncompressed_size: usize,
+}
+
+impl CompressedCertificate {
+    pub fn bytes_saved(&self) -> usize {
+        self.uncompressed_size.saturating_sub(self.compressed_size)
+    }
+}
+
+/// Decompressor recording the sizes of the certificate it decompresses into
+/// the probe of the running handshake.
+#[derive(Debug)]
+struct MeasuredDecompressor(&'static dyn CertDecompressor);
+
+impl CertDecompressor for MeasuredDecompressor {
+    fn decompress(&self, input: &[u8], output: &mut [u8]) -> Result<(), DecompressionFailed> {
+        self.0.decompress(input, output)?;
+        probe::record(|probe| {
+            probe.compressed_certificate = Some(CompressedCertificate {
+                algorithm: self.algorithm(),
+                compressed_size: input.len(),
+                uncompressed_size: output.len(),
+            })
+        });
+        Ok(())
+    }
+
+    fn algorithm(&self) -> CertificateCompressionAlgorithm {
+        self.0.algorithm()
+    }
+}
+
+static ZLIB: MeasuredDecompressor = MeasuredDecompressor(compress::ZLIB_DECOMPRESSOR);
+static BROTLI: MeasuredDecompressor = MeasuredDecompressor(compress::BROTLI_DECOMPRESSOR);
+
+/// Decompressors offering the given algorithm to servers, none disables
+/// certificate compression.
+pub fn cert_decompressors(
+    algorithm: Option<CertificateCompressionAlgorithm>,
+) -> Vec<&'static dyn CertDecompressor> {
+    match algorithm {
+        Some(CertificateCompressionAlgorithm::Zlib) => vec![&ZLIB],
+        Some(CertificateCompressionAlgorithm::Brotli) => vec![&BROTLI],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::compress::CompressionLevel;
+
+    #[tokio::test]
+    async fn test_measured_decompressor() {
+        let certificate = vec![42; 1000];
+        let compressed = compress::ZLIB_COMPRESSOR
+            .compress(certificate.clone(), CompressionLevel::Interactive)
+            .unwrap();
+        let decompressor = cert_decompressors(Some(CertificateCompressionAlgorithm::Zlib))[0];
+
+        let mut output = vec![0; certificate.len()];
+        let probe = probe::scope(async {
+            decompressor.decompress(&compressed, &mut output).unwrap();
+            probe::take()
+        })
+        .await;
+        assert_eq!(output, certificate);
+
+        let compressed_certificate = probe.compressed_certificate.unwrap();
+        assert_eq!(
+            compressed_certificate.algorithm,
+            CertificateCompressionAlgorithm::Zlib
+        );
+        assert_eq!(compressed_certificate.uncompressed_size, 1000);
+        assert_eq!(
+            compressed_certificate.bytes_saved(),
+            1000 - compressed.len()
+        );
+
+        assert!(cert_decompressors(None).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use super::provider::ProviderKind;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rustls::client::EchMode;
+use rustls::pki_types::{pem::PemObject, EchConfigListBytes};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Reads an ECH config list from a PEM file with an ECHCONFIG section or a
+/// file holding the raw list, as published in HTTPS DNS records.
+pub fn load_ech_config_list(path: &Path) -> Result<EchConfigListBytes<'static>, Error> {
+    let contents = std::fs::read(path)
+        .map_err(|e| Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    match EchConfigListBytes::from_pem_slice(&contents) {
+        Ok(config_list) => Ok(config_list),
+        Err(_) if !contents.starts_with(b"-----BEGIN") => Ok(EchConfigListBytes::from(contents)),
+        Err(e) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )),
+    }
+}
+
+/// Decodes a base64 encoded ECH config list.
+pub fn decode_ech_config_list(encoded: &str) -> Result<EchConfigListBytes<'static>, Error> {
+    STANDARD
+        .decode(encoded.trim())
+        .map(EchConfigListBytes::from)
+        .map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid base64 ECH config list: {e}"),
+            )
+        })
+}
+
+/// ECH mode encrypting the ClientHello with the first config of the list
+/// using an HPKE suite we support. Only aws-lc-rs provides HPKE, so ECH is
+/// refused with other crypto providers rather than mixing two of them.
+#[cfg_attr(not(feature = "aws-lc-rs"), allow(unused_variables))]
+pub fn ech_mode(kind: ProviderKind, config_list: EchConfigListBytes) -> Result<EchMode, Error> {
+    match kind {
+        #[cfg(feature = "aws-lc-rs")]
+        ProviderKind::AwsLcRs => rustls::client::EchConfig::new(
+            config_list,
+            rustls::crypto::aws_lc_rs::hpke::ALL_SUPPORTED_SUITES,
+        )
+        .map(EchMode::from)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e)),
+        #[allow(unreachable_patterns)]
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "ECH needs the HPKE suites of aws-lc-rs, not supported with crypto provider {}",
+                kind.name()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_ech_config_list() {
+        let from_file = load_ech_config_list(Path::new("tests/fixtures/ech.pem")).unwrap();
+        let encoded = "AEX+DQBBBwAgACABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4fIAAEAAEAAQAScHVibGljLmV4YW1wbGUuY29tAAA=";
+        let from_base64 = decode_ech_config_list(encoded).unwrap();
+        assert_eq!(from_file.as_ref(), from_base64.as_ref());
+        assert!(decode_ech_config_list("not base64!").is_err());
+    }
+
+    #[cfg(feature = "aws-lc-rs")]
+    #[test]
+    fn test_ech_mode() {
+        let config_list = load_ech_config_list(Path::new("tests/fixtures/ech.pem")).unwrap();
+        assert!(matches!(
+            ech_mode(ProviderKind::AwsLcRs, config_list.clone()),
+            Ok(EchMode::Enable(_))
+        ));
+        assert!(ech_mode(ProviderKind::Ring, config_list).is_err());
+        assert!(ech_mode(ProviderKind::AwsLcRs, EchConfigListBytes::from(vec![0, 1])).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use rustls::client::ResolvesClientCert;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use rustls::sign::CertifiedKey;
+use rustls::SignatureScheme;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Loads a PEM encoded client certificate chain and the matching private key.
+pub fn load_identity(
+    cert_file: &Path,
+    key_file: &Path,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, Error> {
+    let cert_chain = CertificateDer::pem_file_iter(cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {e}", cert_file.display()),
+            )
+        })?;
+    if cert_chain.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("No certificates found in {}", cert_file.display()),
+        ));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_file).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: {e}", key_file.display()),
+        )
+    })?;
+
+    CertifiedKey::from_der(cert_chain, key, provider)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Loads every `<name>.crt` certificate chain in the directory together with
+/// its `<name>.key` private key, ordered by name.
+pub fn load_identity_dir(
+    dir: &Path,
+    provider: &CryptoProvider,
+) -> Result<Vec<Arc<CertifiedKey>>, Error> {
+    let mut cert_files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "crt") {
+            cert_files.push(path);
+        }
+    }
+    cert_files.sort();
+
+    if cert_files.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("No client certificates (*.crt) found in {}", dir.display()),
+        ));
+    }
+
+    cert_files
+        .iter()
+        .map(|cert_file| {
+            load_identity(cert_file, &cert_file.with_extension("key"), provider).map(Arc::new)
+        })
+        .collect()
+}
+
+/// How the next client identity is picked from a pool.
+#[derive(Debug, Clone, Copy)]
+pub enum Rotation {
+    RoundRobin,
+    Random,
+}
+
+/// Client certificate resolver that presents a different identity from the
+/// pool on each handshake, so servers can't serve them all from one cache entry.
+#[derive(Debug)]
+pub struct IdentityPool {
+    identities: Vec<Arc<CertifiedKey>>,
+    rotation: Rotation,
+    next: AtomicUsize,
+    random: RandomState,
+}
+
+impl IdentityPool {
+    pub fn new(identities: Vec<Arc<CertifiedKey>>, rotation: Rotation) -> Self {
+        assert!(!identities.is_empty(), "Identity pool can not be empty");
+        Self {
+            identities,
+            rotation,
+            next: AtomicUsize::new(0),
+            random: RandomState::new(),
+        }
+    }
+
+    fn pick(&self) -> usize {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        match self.rotation {
+            Rotation::RoundRobin => n % self.identities.len(),
+            Rotation::Random => self.random.hash_one(n) as usize % self.identities.len(),
+        }
+    }
+}
+
+impl ResolvesClientCert for IdentityPool {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.identities[self.pick()].clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::provider::default_provider;
+
+    #[test]
+    fn test_load_identity_missing_files() {
+        let result = load_identity(
+            Path::new("does-not-exist.crt"),
+            Path::new("does-not-exist.key"),
+            &default_provider(),
+        );
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("does-not-exist.crt"));
+    }
+
+    /// Fresh directory holding the given fixtures under new names.
+    fn identity_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tls-bench-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        for (fixture, file) in files {
+            std::fs::copy(Path::new("tests/fixtures").join(fixture), dir.join(file)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_load_identity_dir() {
+        let dir = identity_dir(
+            "identities",
+            &[
+                ("client.crt", "b.crt"),
+                ("client.key", "b.key"),
+                ("client.crt", "a.crt"),
+                ("client.key", "a.key"),
+                ("client.pub", "a.pub"),
+            ],
+        );
+        let identities = load_identity_dir(&dir, &default_provider()).unwrap();
+        assert_eq!(identities.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = identity_dir(
+            "unmatched",
+            &[("client.crt", "a.crt"), ("other.key", "a.key")],
+        );
+        assert!(load_identity_dir(&dir, &default_provider()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = identity_dir("empty", &[]);
+        let result = load_identity_dir(&dir, &default_provider());
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("No client certificates"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_identity_pool_round_robin() {
+        let identity = load_identity(
+            Path::new("tests/fixtures/client.crt"),
+            Path::new("tests/fixtures/client.key"),
+            &default_provider(),
+        )
+        .unwrap();
+        let identity = Arc::new(identity);
+        let pool = IdentityPool::new(
+            vec![identity.clone(), identity.clone(), identity],
+            Rotation::RoundRobin,
+        );
+        let picks: Vec<usize> = (0..5).map(|_| pool.pick()).collect();
+        assert_eq!(picks, vec![0, 1, 2, 0, 1]);
+
+        let pool = IdentityPool::new(pool.identities, Rotation::Random);
+        assert!((0..100).all(|_| pool.pick() < 3));
+    }
+}
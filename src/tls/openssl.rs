@@ -0,0 +1,300 @@
+use openssl::nid::Nid;
+use openssl::pkey::Id;
+use openssl::ssl::{
+    SslConnector, SslFiletype, SslMethod, SslOptions, SslRef, SslSession, SslSessionCacheMode,
+    SslVerifyMode, SslVersion,
+};
+use rustls::pki_types::CertificateDer;
+use rustls::{CipherSuite, NamedGroup, ProtocolVersion};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_openssl::SslStream;
+
+use super::tcp_info;
+use super::wire::CountingStream;
+use super::{
+    attempt_resumption, check_alpn, client_auth_verdict, connect, receive_session_tickets,
+    HandshakeFuture, Handshaker, Target, TlsDuration,
+};
+
+/// Trust anchors server certificates are verified against.
+pub enum Roots<'a> {
+    System,
+    CaFile(&'a Path),
+}
+
+/// Handshakes with OpenSSL, which unlike rustls still speaks TLS 1.0 and 1.1.
+pub struct OpensslHandshaker {
+    connector: SslConnector,
+    resumption_ratio: u8,
+    client_auth: bool,
+    session: Arc<Mutex<Option<SslSession>>>,
+}
+
+impl OpensslHandshaker {
+    /// Builds a handshaker offering only the given TLS version. Without roots
+    /// the server certificate is not verified. Sessions are resumed for the
+    /// given share of handshakes, with session IDs only if tickets are disabled.
+    pub fn new(
+        version: SslVersion,
+        roots: Option<Roots>,
+        alpn_protocols: &[String],
+        client_identity: Option<(&Path, &Path)>,
+        resumption_ratio: u8,
+        session_id_only: bool,
+    ) -> Result<Self, Error> {
+        let mut builder = SslConnector::builder(SslMethod::tls_client()).map_err(Error::other)?;
+        builder
+            .set_min_proto_version(Some(version))
+            .and_then(|_| builder.set_max_proto_version(Some(version)))
+            .map_err(Error::other)?;
+        // OpenSSL 3 refuses TLS 1.0 and 1.1 at the default security level.
+        if version == SslVersion::TLS1 || version == SslVersion::TLS1_1 {
+            builder.set_security_level(0);
+        }
+
+        match roots {
+            None => builder.set_verify(SslVerifyMode::NONE),
+            Some(Roots::System) => builder.set_default_verify_paths().map_err(Error::other)?,
+            Some(Roots::CaFile(ca_file)) => builder.set_ca_file(ca_file).map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{}: {e}", ca_file.display()),
+                )
+            })?,
+        }
+
+        if !alpn_protocols.is_empty() {
+            let mut wire = Vec::new();
+            for protocol in alpn_protocols {
+                wire.push(protocol.len() as u8);
+                wire.extend_from_slice(protocol.as_bytes());
+            }
+            builder.set_alpn_protos(&wire).map_err(Error::other)?;
+        }
+
+        if let Some((cert_file, key_file)) = client_identity {
+            builder
+                .set_certificate_chain_file(cert_file)
+                .and_then(|_| builder.set_private_key_file(key_file, SslFiletype::PEM))
+                .and_then(|_| builder.check_private_key())
+                .map_err(|e| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{}: {e}", cert_file.display()),
+                    )
+                })?;
+        }
+
+        let session = Arc::new(Mutex::new(None));
+        if resumption_ratio > 0 {
+            builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+            let latest_session = session.clone();
+            builder.set_new_session_callback(move |_, session| {
+                *latest_session.lock().unwrap() = Some(session);
+            });
+            if session_id_only {
+                builder.set_options(SslOptions::NO_TICKET);
+            }
+        }
+
+        Ok(Self {
+            connector: builder.build(),
+            resumption_ratio,
+            client_auth: client_identity.is_some(),
+            session,
+        })
+    }
+
+    async fn handshake(
+        &self,
+        target: &Target,
+        resumption: bool,
+    ) -> Result<(TlsDuration, SslStream<CountingStream<TcpStream>>), Error> {
+        let tcp_now = Instant::now();
+        let stream = connect(target).await?;
+        let tcp_connect_duration = tcp_now.elapsed();
+        let rtt = tcp_info::tcp_info(&stream).map(|info| info.rtt);
+        let stream = CountingStream::new(stream);
+
+        let mut ssl = self
+            .connector
+            .configure()
+            .and_then(|config| config.into_ssl(&target.server_name.to_str()))
+            .map_err(Error::other)?;
+        if resumption {
+            if let Some(session) = self.session.lock().unwrap().as_ref() {
+                // Safety: the session was negotiated with the context of this connector.
+                unsafe { ssl.set_session(session) }.map_err(Error::other)?;
+            }
+        }
+        let mut tls_stream = SslStream::new(ssl, stream).map_err(Error::other)?;
+
+        let handshake_now = Instant::now();
+        Pin::new(&mut tls_stream)
+            .connect()
+            .await
+            .map_err(ssl_error)?;
+        let handshake_done = Instant::now();
+        let handshake_duration = handshake_done - handshake_now;
+        let wire = tls_stream.get_ref().stats();
+        let phases = tls_stream.get_ref().phases(handshake_done);
+        let retransmits =
+            tcp_info::tcp_info(tls_stream.get_ref().get_ref()).map(|info| info.retransmits);
+
+        tls_stream.shutdown().await?;
+
+        let ssl = tls_stream.ssl();
+        let cert_chain: Vec<Vec<u8>> = ssl
+            .peer_cert_chain()
+            .map(|chain| chain.iter().filter_map(|cert| cert.to_der().ok()).collect())
+            .unwrap_or_default();
+        let alpn_protocol = ssl
+            .selected_alpn_protocol()
+            .map(|protocol| protocol.to_vec());
+        check_alpn(target, alpn_protocol.as_deref())?;
+
+        Ok((
+            TlsDuration {
+                handshaker: "openssl",
+                tcp_connect: tcp_connect_duration,
+                handshake: handshake_duration,
+                resumption_attempted: resumption,
+                resumed: ssl.session_reused(),
+                hello_retry_request: tls_stream.get_ref().hello_retry_request(),
+                early_data_accepted: None,
+                protocol_version: protocol_version(ssl),
+                cipher_suite: ssl
+                    .current_cipher()
+                    .map(|cipher| CipherSuite::from(u16::from_be_bytes(cipher.protocol_id()))),
+                kx_group: kx_group(ssl),
+                signature_scheme: None,
+                alpn_protocol,
+                cert_chain_len: cert_chain.len(),
+                cert_chain_size: cert_chain.iter().map(|cert| cert.len()).sum(),
+                end_entity_cert: cert_chain.first().cloned().map(CertificateDer::from),
+                raw_public_key: false,
+                compressed_certificate: None,
+                ech_status: None,
+                wire,
+                phases,
+                cert_verification: None,
+                signature_verification: None,
+                transfer: None,
+                transfer_bytes: 0,
+                rtt,
+                retransmits,
+            },
+            tls_stream,
+        ))
+    }
+
+    async fn handshake_with_timeout(
+        &self,
+        target: &Target,
+        resumption: bool,
+    ) -> Result<TlsDuration, Error> {
+        let handshake_timeout = timeout(
+            Duration::from_millis(target.timeout_ms),
+            self.handshake(target, resumption),
+        );
+        let (latencies, mut tls_stream) = handshake_timeout.await??;
+
+        if self.client_auth && tls_stream.ssl().version2() == Some(SslVersion::TLS1_3) {
+            client_auth_verdict(target, &mut tls_stream, ssl_alert).await?;
+        }
+
+        if self.resumption_ratio > 0 && tls_stream.ssl().version2() == Some(SslVersion::TLS1_3) {
+            receive_session_tickets(target, &mut tls_stream).await;
+        }
+
+        Ok(latencies)
+    }
+}
+
+impl Handshaker for OpensslHandshaker {
+    fn handshake<'a>(&'a self, target: &'a Target, n: usize) -> HandshakeFuture<'a> {
+        let resumption = attempt_resumption(n, self.resumption_ratio);
+        Box::pin(self.handshake_with_timeout(target, resumption))
+    }
+}
+
+/// Keeps only the reason of an OpenSSL error, so handshake failures group
+/// into a few error classes instead of one per error stack.
+fn ssl_error(error: openssl::ssl::Error) -> Error {
+    let reason = error
+        .ssl_error()
+        .and_then(|stack| stack.errors().first())
+        .and_then(|error| error.reason());
+    match reason {
+        Some(reason) => Error::new(ErrorKind::InvalidData, format!("OpenSSL error: {reason}")),
+        None => error.into_io_error().unwrap_or_else(Error::other),
+    }
+}
+
+/// Keeps read errors carrying an alert the server sent, classified like
+/// handshake failures.
+fn ssl_alert(error: Error) -> Option<Error> {
+    let alert = error
+        .get_ref()
+        .and_then(|error| error.downcast_ref::<openssl::ssl::Error>())
+        .is_some_and(|error| error.ssl_error().is_some());
+    if !alert {
+        return None;
+    }
+    let error = error.into_inner()?.downcast::<openssl::ssl::Error>().ok()?;
+    Some(ssl_error(*error))
+}
+
+fn protocol_version(ssl: &SslRef) -> Option<ProtocolVersion> {
+    match ssl.version2()? {
+        SslVersion::TLS1 => Some(ProtocolVersion::TLSv1_0),
+        SslVersion::TLS1_1 => Some(ProtocolVersion::TLSv1_1),
+        SslVersion::TLS1_2 => Some(ProtocolVersion::TLSv1_2),
+        SslVersion::TLS1_3 => Some(ProtocolVersion::TLSv1_3),
+        _ => None,
+    }
+}
+
+/// Key exchange group of the server's ephemeral key share.
+fn kx_group(ssl: &SslRef) -> Option<NamedGroup> {
+    let key = ssl.peer_tmp_key().ok()?;
+    match key.id() {
+        Id::X25519 => Some(NamedGroup::X25519),
+        Id::X448 => Some(NamedGroup::X448),
+        Id::EC => match key.ec_key().ok()?.group().curve_name()? {
+            Nid::X9_62_PRIME256V1 => Some(NamedGroup::secp256r1),
+            Nid::SECP384R1 => Some(NamedGroup::secp384r1),
+            Nid::SECP521R1 => Some(NamedGroup::secp521r1),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openssl_handshaker_missing_ca_file() {
+        let result = OpensslHandshaker::new(
+            SslVersion::TLS1_2,
+            Some(Roots::CaFile(Path::new("does-not-exist.pem"))),
+            &[],
+            None,
+            0,
+            false,
+        );
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("does-not-exist.pem"));
+    }
+}
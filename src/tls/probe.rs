@@ -0,0 +1,63 @@
+use rustls::SignatureScheme;
+use std::cell::RefCell;
+use std::future::Future;
+use tokio::time::Duration;
+
+use super::compression::CompressedCertificate;
+
+/// What rustls callbacks observed during a handshake, as they have no way to
+/// return it to the code driving the handshake.
+#[derive(Debug, Default, Clone)]
+pub struct Probe {
+    pub compressed_certificate: Option<CompressedCertificate>,
+    /// Scheme of the server's handshake signature, unless the handshake was resumed
+    pub signature_scheme: Option<SignatureScheme>,
+    /// Set when the server authenticated with a raw public key
+    pub raw_public_key: bool,
+    /// Time the client spent verifying the server's certificate
+    pub cert_verification: Option<Duration>,
+    /// Time the client spent verifying the server's handshake signatures
+    pub signature_verification: Option<Duration>,
+}
+
+tokio::task_local! {
+    static PROBE: RefCell<Probe>;
+}
+
+/// Runs a handshake with a fresh probe its callbacks record into.
+pub async fn scope<F: Future>(future: F) -> F::Output {
+    PROBE.scope(RefCell::new(Probe::default()), future).await
+}
+
+/// Records into the probe of the running handshake, outside of one it does nothing.
+pub fn record(f: impl FnOnce(&mut Probe)) {
+    let _ = PROBE.try_with(|probe| f(&mut probe.borrow_mut()));
+}
+
+/// Takes what the running handshake recorded so far.
+pub fn take() -> Probe {
+    PROBE.try_with(|probe| probe.take()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_probe_scope() {
+        let compressed_certificate = CompressedCertificate {
+            algorithm: rustls::CertificateCompressionAlgorithm::Zlib,
+            compressed_size: 1,
+            uncompressed_size: 2,
+        };
+        record(|probe| probe.compressed_certificate = Some(compressed_certificate));
+        assert!(take().compressed_certificate.is_none());
+
+        let probe = scope(async {
+            record(|probe| probe.compressed_certificate = Some(compressed_certificate));
+            take()
+        })
+        .await;
+        assert_eq!(probe.compressed_certificate, Some(compressed_certificate));
+    }
+}
@@ -0,0 +1,281 @@
+use rustls::crypto::{CryptoProvider, SupportedKxGroup};
+use std::io::{Error, ErrorKind};
+
+#[cfg(not(any(feature = "aws-lc-rs", feature = "ring")))]
+compile_error!("At least one of the aws-lc-rs or ring features must be enabled");
+
+/// Crypto libraries rustls can be backed by, available when the cargo feature
+/// of the same name is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    AwsLcRs,
+    Ring,
+}
+
+impl Default for ProviderKind {
+    fn default() -> Self {
+        if cfg!(feature = "aws-lc-rs") {
+            Self::AwsLcRs
+        } else {
+            Self::Ring
+        }
+    }
+}
+
+impl ProviderKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AwsLcRs => "aws-lc-rs",
+            Self::Ring => "ring",
+        }
+    }
+
+    /// Default provider of the library. FIPS builds only offer the FIPS
+    /// approved algorithms of aws-lc-rs.
+    pub fn provider(self) -> Result<CryptoProvider, Error> {
+        match self {
+            #[cfg(feature = "fips")]
+            Self::AwsLcRs => Ok(rustls::crypto::default_fips_provider()),
+            #[cfg(all(feature = "aws-lc-rs", not(feature = "fips")))]
+            Self::AwsLcRs => Ok(rustls::crypto::aws_lc_rs::default_provider()),
+            #[cfg(feature = "fips")]
+            Self::Ring => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Crypto provider ring is not FIPS approved, use aws-lc-rs",
+            )),
+            #[cfg(all(feature = "ring", not(feature = "fips")))]
+            Self::Ring => Ok(rustls::crypto::ring::default_provider()),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.not_compiled()),
+        }
+    }
+
+    /// All key exchange groups the library implements, including the ones
+    /// its default provider doesn't offer. FIPS builds only get the approved ones.
+    pub fn all_kx_groups(self) -> Result<Vec<&'static dyn SupportedKxGroup>, Error> {
+        let groups: &[&'static dyn SupportedKxGroup] = match self {
+            #[cfg(feature = "aws-lc-rs")]
+            Self::AwsLcRs => rustls::crypto::aws_lc_rs::ALL_KX_GROUPS,
+            #[cfg(feature = "ring")]
+            Self::Ring => rustls::crypto::ring::ALL_KX_GROUPS,
+            #[allow(unreachable_patterns)]
+            _ => return Err(self.not_compiled()),
+        };
+        Ok(groups
+            .iter()
+            .filter(|group| !cfg!(feature = "fips") || group.fips())
+            .copied()
+            .collect())
+    }
+
+    fn not_compiled(self) -> Error {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Crypto provider {} is not compiled in, rebuild with --features {}",
+                self.name(),
+                self.name()
+            ),
+        )
+    }
+}
+
+/// Provider of the default crypto library.
+pub fn default_provider() -> CryptoProvider {
+    ProviderKind::default()
+        .provider()
+        .expect("Default crypto provider is always compiled in")
+}
+
+/// Restricts the provider to the named cipher suites, in the given order of
+/// preference. Names are matched case insensitively, e.g. `TLS13_AES_128_GCM_SHA256`.
+pub fn with_cipher_suites(
+    mut provider: CryptoProvider,
+    names: &[String],
+) -> Result<CryptoProvider, Error> {
+    let mut cipher_suites = Vec::with_capacity(names.len());
+    for name in names {
+        let suite = provider
+            .cipher_suites
+            .iter()
+            .find(|suite| format!("{:?}", suite.suite()).eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let supported: Vec<String> = provider
+                    .cipher_suites
+                    .iter()
+                    .map(|suite| format!("{:?}", suite.suite()))
+                    .collect();
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Unsupported cipher suite {name}, supported are {}",
+                        supported.join(", ")
+                    ),
+                )
+            })?;
+        cipher_suites.push(*suite);
+    }
+
+    provider.cipher_suites = cipher_suites;
+    Ok(provider)
+}
+
+/// Restricts the provider to the named key exchange groups, in the given order
+/// of preference. The first group is the one a key share is sent for in the
+/// ClientHello. Names are matched case insensitively, e.g. `X25519MLKEM768`.
+pub fn with_kx_groups(
+    mut provider: CryptoProvider,
+    kind: ProviderKind,
+    names: &[String],
+) -> Result<CryptoProvider, Error> {
+    let supported = kind.all_kx_groups()?;
+    let mut kx_groups = Vec::with_capacity(names.len());
+    for name in names {
+        let group = supported
+            .iter()
+            .find(|group| format!("{:?}", group.name()).eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let supported: Vec<String> = supported
+                    .iter()
+                    .map(|group| format!("{:?}", group.name()))
+                    .collect();
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Unsupported key exchange group {name}, supported are {}",
+                        supported.join(", ")
+                    ),
+                )
+            })?;
+        kx_groups.push(*group);
+    }
+
+    provider.kx_groups = kx_groups;
+    Ok(provider)
+}
+
+/// Moves the named group to the front of the provider's key exchange groups,
+/// so the initial key share is sent for it while the others stay supported.
+/// Servers preferring one of the other groups answer with a HelloRetryRequest.
+pub fn with_initial_key_share(
+    mut provider: CryptoProvider,
+    kind: ProviderKind,
+    name: &str,
+) -> Result<CryptoProvider, Error> {
+    let group = with_kx_groups(provider.clone(), kind, &[name.to_string()])?.kx_groups[0];
+    provider
+        .kx_groups
+        .retain(|supported| supported.name() != group.name());
+    provider.kx_groups.insert(0, group);
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::{CipherSuite, NamedGroup};
+
+    #[test]
+    fn test_with_cipher_suites() {
+        let names = vec![
+            String::from("tls13_aes_256_gcm_sha384"),
+            String::from("TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256"),
+        ];
+        let provider = with_cipher_suites(default_provider(), &names).unwrap();
+        let suites: Vec<CipherSuite> = provider.cipher_suites.iter().map(|s| s.suite()).collect();
+        assert_eq!(
+            suites,
+            vec![
+                CipherSuite::TLS13_AES_256_GCM_SHA384,
+                CipherSuite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_cipher_suites_unknown() {
+        let names = vec![String::from("TLS_RSA_WITH_RC4_128_MD5")];
+        let result = with_cipher_suites(default_provider(), &names);
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("Unsupported cipher suite TLS_RSA_WITH_RC4_128_MD5"));
+    }
+
+    #[test]
+    fn test_with_kx_groups() {
+        let kind = ProviderKind::default();
+        let names = vec![String::from("secp384r1"), String::from("SECP256R1")];
+        let provider = with_kx_groups(default_provider(), kind, &names).unwrap();
+        let groups: Vec<NamedGroup> = provider.kx_groups.iter().map(|g| g.name()).collect();
+        assert_eq!(groups, vec![NamedGroup::secp384r1, NamedGroup::secp256r1]);
+
+        #[cfg(feature = "aws-lc-rs")]
+        {
+            let kind = ProviderKind::AwsLcRs;
+            let names = vec![String::from("x25519mlkem768"), String::from("secp384r1")];
+            let provider = with_kx_groups(kind.provider().unwrap(), kind, &names).unwrap();
+            let groups: Vec<NamedGroup> = provider.kx_groups.iter().map(|g| g.name()).collect();
+            assert_eq!(
+                groups,
+                vec![NamedGroup::X25519MLKEM768, NamedGroup::secp384r1]
+            );
+        }
+
+        let names = vec![String::from("ffdhe2048")];
+        assert!(with_kx_groups(default_provider(), kind, &names).is_err());
+    }
+
+    #[test]
+    fn test_with_initial_key_share() {
+        let kind = ProviderKind::default();
+        let names = vec![String::from("secp256r1")];
+        let provider = with_kx_groups(default_provider(), kind, &names).unwrap();
+        let provider = with_initial_key_share(provider, kind, "secp384r1").unwrap();
+        let groups: Vec<NamedGroup> = provider.kx_groups.iter().map(|g| g.name()).collect();
+        assert_eq!(groups, vec![NamedGroup::secp384r1, NamedGroup::secp256r1]);
+
+        let provider = with_initial_key_share(provider, kind, "secp256r1").unwrap();
+        let groups: Vec<NamedGroup> = provider.kx_groups.iter().map(|g| g.name()).collect();
+        assert_eq!(groups, vec![NamedGroup::secp256r1, NamedGroup::secp384r1]);
+
+        let supported = default_provider().kx_groups.len();
+        let provider = with_initial_key_share(default_provider(), kind, "secp384r1").unwrap();
+        assert_eq!(provider.kx_groups[0].name(), NamedGroup::secp384r1);
+        assert_eq!(provider.kx_groups.len(), supported);
+    }
+
+    #[cfg(feature = "fips")]
+    #[test]
+    fn test_fips_provider() {
+        let kind = ProviderKind::default();
+        assert!(default_provider().fips());
+
+        let names = vec![String::from("TLS13_CHACHA20_POLY1305_SHA256")];
+        assert!(with_cipher_suites(default_provider(), &names).is_err());
+        let names = vec![String::from("X25519")];
+        assert!(with_kx_groups(default_provider(), kind, &names).is_err());
+
+        let names = vec![String::from("TLS13_AES_128_GCM_SHA256")];
+        let provider = with_cipher_suites(default_provider(), &names).unwrap();
+        let names = vec![String::from("secp256r1"), String::from("secp384r1")];
+        let provider = with_kx_groups(provider, kind, &names).unwrap();
+        assert!(provider.fips());
+    }
+
+    #[test]
+    fn test_provider_kind() {
+        let kind = ProviderKind::default();
+        assert!(kind.provider().is_ok());
+        assert!(!kind.all_kx_groups().unwrap().is_empty());
+
+        #[cfg(not(any(feature = "ring", feature = "fips")))]
+        assert!(ProviderKind::Ring
+            .provider()
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("--features ring"));
+    }
+}
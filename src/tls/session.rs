@@ -0,0 +1,67 @@
+use rustls::client::{ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue};
+use rustls::pki_types::ServerName;
+use rustls::NamedGroup;
+use std::sync::Arc;
+
+/// Session store that never remembers which key exchange group a server
+/// picked, so every ClientHello starts with the configured initial key share.
+#[derive(Debug)]
+pub struct NoKxHintStore(Arc<dyn ClientSessionStore>);
+
+impl NoKxHintStore {
+    pub fn new(store: Arc<dyn ClientSessionStore>) -> Self {
+        Self(store)
+    }
+}
+
+impl ClientSessionStore for NoKxHintStore {
+    fn set_kx_hint(&self, _server_name: ServerName<'static>, _group: NamedGroup) {}
+
+    fn kx_hint(&self, _server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        None
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.0.set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.0.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.0.remove_tls12_session(server_name)
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: Tls13ClientSessionValue,
+    ) {
+        self.0.insert_tls13_ticket(server_name, value)
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<Tls13ClientSessionValue> {
+        self.0.take_tls13_ticket(server_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::client::ClientSessionMemoryCache;
+
+    #[test]
+    fn test_no_kx_hint_store() {
+        let inner = Arc::new(ClientSessionMemoryCache::new(8));
+        let store = NoKxHintStore::new(inner.clone());
+        let server_name = ServerName::try_from("example.com").unwrap();
+
+        store.set_kx_hint(server_name.clone(), NamedGroup::X25519);
+        assert_eq!(store.kx_hint(&server_name), None);
+        assert_eq!(inner.kx_hint(&server_name), None);
+    }
+}
@@ -0,0 +1,102 @@
+use rustls::ProtocolVersion;
+use tokio::net::TcpStream;
+use tokio::time::Duration;
+
+/// What the kernel measured on the TCP connection of a handshake.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TcpInfo {
+    /// Smoothed round trip time
+    pub rtt: Duration,
+    /// Segments retransmitted since the connection was opened
+    pub retransmits: u32,
+}
+
+/// Reads `TCP_INFO` of the socket, which is only available on Linux.
+#[cfg(target_os = "linux")]
+pub fn tcp_info(stream: &TcpStream) -> Option<TcpInfo> {
+    use std::os::fd::AsRawFd;
+
+    // Safety: tcp_info is plain data and the kernel writes at most `len` bytes of it.
+    let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            &mut info as *mut libc::tcp_info as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    Some(TcpInfo {
+        rtt: Duration::from_micros(info.tcpi_rtt.into()),
+        retransmits: info.tcpi_total_retrans,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn tcp_info(_stream: &TcpStream) -> Option<TcpInfo> {
+    None
+}
+
+/// Network round trips the client waits for during a handshake. A full TLS 1.2
+/// handshake takes two, TLS 1.3 and resumed TLS 1.2 handshakes one, plus one
+/// more for a HelloRetryRequest.
+pub fn round_trips(
+    protocol_version: ProtocolVersion,
+    resumed: bool,
+    hello_retry_request: bool,
+) -> u32 {
+    let round_trips = match protocol_version {
+        ProtocolVersion::TLSv1_3 => 1,
+        _ if resumed => 1,
+        _ => 2,
+    };
+    round_trips + u32::from(hello_retry_request)
+}
+
+/// Estimates the time the server spent processing a handshake, the part of
+/// the handshake latency the network round trips don't account for.
+pub fn server_processing(handshake: Duration, rtt: Duration, round_trips: u32) -> Duration {
+    handshake.saturating_sub(rtt * round_trips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_server_processing() {
+        let rtt = Duration::from_millis(10);
+        let full_tls12 = round_trips(ProtocolVersion::TLSv1_2, false, false);
+        assert_eq!(full_tls12, 2);
+        assert_eq!(round_trips(ProtocolVersion::TLSv1_2, true, false), 1);
+        assert_eq!(round_trips(ProtocolVersion::TLSv1_3, false, false), 1);
+        assert_eq!(round_trips(ProtocolVersion::TLSv1_3, false, true), 2);
+        assert_eq!(
+            server_processing(Duration::from_millis(25), rtt, full_tls12),
+            Duration::from_millis(5)
+        );
+        assert_eq!(
+            server_processing(Duration::from_millis(15), rtt, full_tls12),
+            Duration::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tcp_info() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let info = tcp_info(&stream);
+        assert_eq!(info.is_some(), cfg!(target_os = "linux"));
+        if let Some(info) = info {
+            assert_eq!(info.retransmits, 0);
+        }
+    }
+}
@@ -1,7 +1,96 @@
-use rustls::client::danger::HandshakeSignatureValid;
-use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
-use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
-use rustls::DigitallySignedStruct;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{
+    verify_tls12_signature, verify_tls13_signature, verify_tls13_signature_with_raw_key,
+    CryptoProvider,
+};
+use rustls::pki_types::{
+    pem::PemObject, CertificateDer, CertificateRevocationListDer, ServerName,
+    SubjectPublicKeyInfoDer, UnixTime,
+};
+use rustls::{
+    CertificateError, DigitallySignedStruct, DistinguishedName, PeerIncompatible, RootCertStore,
+    SignatureScheme,
+};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::time::Instant;
+
+use super::probe;
+
+/// Roots from the Mozilla CA store bundled into the binary.
+pub fn webpki_roots() -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    roots
+}
+
+/// Roots trusted by the operating system.
+pub fn system_roots() -> Result<RootCertStore, Error> {
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    if roots.is_empty() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "No certificates found in the system store",
+        ));
+    }
+    Ok(roots)
+}
+
+/// Roots from a PEM encoded CA bundle.
+pub fn ca_file_roots(path: &Path) -> Result<RootCertStore, Error> {
+    let mut roots = RootCertStore::empty();
+    for cert in
+        CertificateDer::pem_file_iter(path).map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+    {
+        let cert = cert.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        roots
+            .add(cert)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    }
+    if roots.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("No certificates found in {}", path.display()),
+        ));
+    }
+    Ok(roots)
+}
+
+/// Reads the CRLs of a PEM file, or of a file holding a single DER encoded CRL.
+pub fn load_crls(path: &Path) -> Result<Vec<CertificateRevocationListDer<'static>>, Error> {
+    let contents = std::fs::read(path)
+        .map_err(|e| Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    if !contents.starts_with(b"-----BEGIN") {
+        return Ok(vec![CertificateRevocationListDer::from(contents)]);
+    }
+    let crls = CertificateRevocationListDer::pem_slice_iter(&contents)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {e}", path.display())))?;
+    if crls.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("No CRLs found in {}", path.display()),
+        ));
+    }
+    Ok(crls)
+}
+
+/// Verifier checking certificates against the roots. With CRLs it also checks
+/// revocation, accepting certificates whose issuer has no CRL loaded.
+pub fn webpki_verifier(
+    roots: RootCertStore,
+    provider: CryptoProvider,
+    crls: Vec<CertificateRevocationListDer<'static>>,
+) -> Result<Arc<WebPkiServerVerifier>, Error> {
+    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), Arc::new(provider))
+        .with_crls(crls)
+        .allow_unknown_revocation_status()
+        .build()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
 
 #[derive(Debug)]
 pub struct NoCertificateVerification(CryptoProvider);
@@ -12,7 +101,7 @@ impl NoCertificateVerification {
     }
 }
 
-impl rustls::client::danger::ServerCertVerifier for NoCertificateVerification {
+impl ServerCertVerifier for NoCertificateVerification {
     fn verify_server_cert(
         &self,
         _end_entity: &CertificateDer<'_>,
@@ -20,8 +109,8 @@ impl rustls::client::danger::ServerCertVerifier for NoCertificateVerification {
         _server_name: &ServerName<'_>,
         _ocsp: &[u8],
         _now: UnixTime,
-    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
-        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
     }
 
     fn verify_tls12_signature(
@@ -52,7 +141,329 @@ impl rustls::client::danger::ServerCertVerifier for NoCertificateVerification {
         )
     }
 
-    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
         self.0.signature_verification_algorithms.supported_schemes()
     }
 }
+
+/// Verifies servers authenticating with a raw public key (RFC 7250) instead of
+/// a certificate, accepting only the pinned key. rustls supports raw public
+/// keys with TLS 1.3 only.
+#[derive(Debug)]
+pub struct RawPublicKeyVerifier {
+    public_key: SubjectPublicKeyInfoDer<'static>,
+    provider: CryptoProvider,
+}
+
+impl RawPublicKeyVerifier {
+    pub fn new(public_key: SubjectPublicKeyInfoDer<'static>, provider: CryptoProvider) -> Self {
+        Self {
+            public_key,
+            provider,
+        }
+    }
+
+    /// Pins the key from a PEM encoded public key file.
+    pub fn from_pem_file(path: &Path, provider: CryptoProvider) -> Result<Self, Error> {
+        let public_key = SubjectPublicKeyInfoDer::from_pem_file(path)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {e}", path.display())))?;
+        Ok(Self::new(public_key, provider))
+    }
+}
+
+impl ServerCertVerifier for RawPublicKeyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if end_entity.as_ref() != self.public_key.as_ref() {
+            return Err(CertificateError::ApplicationVerificationFailure.into());
+        }
+        probe::record(|probe| probe.raw_public_key = true);
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Err(PeerIncompatible::Tls12NotOffered.into())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature_with_raw_key(
+            message,
+            &SubjectPublicKeyInfoDer::from(cert.as_ref()),
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+
+    fn requires_raw_public_keys(&self) -> bool {
+        true
+    }
+}
+
+/// Looks up the named signature schemes the provider can verify, keeping the
+/// given order. Names are matched case insensitively, e.g. `ECDSA_NISTP256_SHA256`.
+pub fn signature_schemes(
+    provider: &CryptoProvider,
+    names: &[String],
+) -> Result<Vec<SignatureScheme>, Error> {
+    let supported = provider
+        .signature_verification_algorithms
+        .supported_schemes();
+    names
+        .iter()
+        .map(|name| {
+            supported
+                .iter()
+                .find(|scheme| format!("{scheme:?}").eq_ignore_ascii_case(name))
+                .copied()
+                .ok_or_else(|| {
+                    let supported: Vec<String> = supported
+                        .iter()
+                        .map(|scheme| format!("{scheme:?}"))
+                        .collect();
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Unsupported signature scheme {name}, supported are {}",
+                            supported.join(", ")
+                        ),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Wraps a verifier to offer servers only the given signature schemes, which
+/// steers servers with several certificates to one of them. Records the scheme
+/// the server signed the handshake with and the time verification took, to
+/// tell the client's crypto apart from waiting on the server.
+#[derive(Debug)]
+pub struct InstrumentedVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    schemes: Option<Vec<SignatureScheme>>,
+}
+
+impl InstrumentedVerifier {
+    /// Without schemes all the ones the inner verifier supports are offered.
+    pub fn new(inner: Arc<dyn ServerCertVerifier>, schemes: Option<Vec<SignatureScheme>>) -> Self {
+        Self { inner, schemes }
+    }
+
+    fn verify_signature(
+        verify: impl FnOnce() -> Result<HandshakeSignatureValid, rustls::Error>,
+        scheme: SignatureScheme,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        let verify_now = Instant::now();
+        let result = verify();
+        let elapsed = verify_now.elapsed();
+        probe::record(|probe| {
+            *probe.signature_verification.get_or_insert_default() += elapsed;
+            if result.is_ok() {
+                probe.signature_scheme = Some(scheme);
+            }
+        });
+        result
+    }
+}
+
+impl ServerCertVerifier for InstrumentedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verify_now = Instant::now();
+        let result =
+            self.inner
+                .verify_server_cert(end_entity, intermediates, server_name, ocsp, now);
+        let elapsed = verify_now.elapsed();
+        probe::record(|probe| probe.cert_verification = Some(elapsed));
+        result
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Self::verify_signature(
+            || self.inner.verify_tls12_signature(message, cert, dss),
+            dss.scheme,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Self::verify_signature(
+            || self.inner.verify_tls13_signature(message, cert, dss),
+            dss.scheme,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        let supported = self.inner.supported_verify_schemes();
+        match &self.schemes {
+            Some(schemes) => schemes
+                .iter()
+                .filter(|scheme| supported.contains(scheme))
+                .copied()
+                .collect(),
+            None => supported,
+        }
+    }
+
+    fn requires_raw_public_keys(&self) -> bool {
+        self.inner.requires_raw_public_keys()
+    }
+
+    fn root_hint_subjects(&self) -> Option<&[DistinguishedName]> {
+        self.inner.root_hint_subjects()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ca_file_roots_missing_file() {
+        let result = ca_file_roots(Path::new("does-not-exist.pem"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_webpki_verifier() {
+        let provider = crate::tls::provider::default_provider();
+        assert!(webpki_verifier(webpki_roots(), provider, Vec::new()).is_ok());
+        let provider = crate::tls::provider::default_provider();
+        assert!(webpki_verifier(RootCertStore::empty(), provider, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_load_crls() {
+        let crls = load_crls(Path::new("tests/fixtures/client.crl")).unwrap();
+        assert_eq!(crls.len(), 1);
+        let provider = crate::tls::provider::default_provider();
+        assert!(webpki_verifier(webpki_roots(), provider, crls).is_ok());
+
+        assert!(load_crls(Path::new("tests/fixtures/client.crt")).is_err());
+        assert!(load_crls(Path::new("does-not-exist.crl")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_instrumented_verifier_records_verification() {
+        let provider = crate::tls::provider::default_provider();
+        let inner = Arc::new(NoCertificateVerification::new(provider));
+        let verifier = InstrumentedVerifier::new(inner, None);
+        let server_name = ServerName::try_from("localhost").unwrap();
+
+        let probe = probe::scope(async {
+            verifier
+                .verify_server_cert(
+                    &CertificateDer::from(&b"certificate"[..]),
+                    &[],
+                    &server_name,
+                    &[],
+                    UnixTime::now(),
+                )
+                .unwrap();
+            probe::take()
+        })
+        .await;
+        assert!(probe.cert_verification.is_some());
+        assert!(probe.signature_verification.is_none());
+    }
+
+    #[test]
+    fn test_raw_public_key_verifier() {
+        let provider = crate::tls::provider::default_provider();
+        let verifier =
+            RawPublicKeyVerifier::from_pem_file(Path::new("tests/fixtures/client.pub"), provider)
+                .unwrap();
+        assert!(verifier.requires_raw_public_keys());
+
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let verify = |key: &[u8]| {
+            verifier.verify_server_cert(
+                &CertificateDer::from(key),
+                &[],
+                &server_name,
+                &[],
+                UnixTime::now(),
+            )
+        };
+        let public_key =
+            SubjectPublicKeyInfoDer::from_pem_file("tests/fixtures/client.pub").unwrap();
+        assert!(verify(public_key.as_ref()).is_ok());
+        assert!(verify(b"another key").is_err());
+
+        let provider = crate::tls::provider::default_provider();
+        assert!(RawPublicKeyVerifier::from_pem_file(
+            Path::new("tests/fixtures/client.crt"),
+            provider
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_instrumented_verifier() {
+        let provider = crate::tls::provider::default_provider();
+        let names = vec![
+            String::from("rsa_pss_sha256"),
+            String::from("ECDSA_NISTP256_SHA256"),
+        ];
+        let schemes = signature_schemes(&provider, &names).unwrap();
+        assert_eq!(
+            schemes,
+            [
+                SignatureScheme::RSA_PSS_SHA256,
+                SignatureScheme::ECDSA_NISTP256_SHA256
+            ]
+        );
+        assert!(signature_schemes(&provider, &[String::from("DSA")]).is_err());
+
+        let inner = Arc::new(NoCertificateVerification::new(provider));
+        let verifier = InstrumentedVerifier::new(inner.clone(), Some(schemes));
+        assert_eq!(
+            verifier.supported_verify_schemes(),
+            [
+                SignatureScheme::RSA_PSS_SHA256,
+                SignatureScheme::ECDSA_NISTP256_SHA256
+            ]
+        );
+        let verifier = InstrumentedVerifier::new(inner.clone(), None);
+        assert_eq!(
+            verifier.supported_verify_schemes(),
+            inner.supported_verify_schemes()
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::Error;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Duration, Instant};
+
+/// Bytes and I/O calls a handshake needed on the wire.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WireStats {
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+    pub writes: usize,
+    pub reads: usize,
+    /// Bytes the client sent before the server answered, the ClientHello
+    /// plus any early data. After a HelloRetryRequest, the retried ClientHello
+    pub client_hello: usize,
+    /// Bytes the server sent in answer to the ClientHello, before the client
+    /// sent its next flight. After a HelloRetryRequest, the answer to the retry
+    pub server_flight: usize,
+}
+
+/// Handshake timeline split at the flights seen on the wire. Time to first
+/// byte is mostly network RTT, the server flight adds the server's crypto and
+/// certificate transfer and Finished to done is the last round trip, if any.
+/// After a HelloRetryRequest the phases restart at the retried ClientHello.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HandshakePhases {
+    /// From the last ClientHello byte sent to the first server byte received
+    pub time_to_first_byte: Option<Duration>,
+    /// From the first to the last byte of the server's first flight
+    pub server_flight: Option<Duration>,
+    /// From the client's second flight, ending with its Finished, to the end
+    /// of the handshake
+    pub finished_to_done: Option<Duration>,
+}
+
+/// Random of a ServerHello that is a HelloRetryRequest, RFC 8446 section 4.1.3.
+const HELLO_RETRY_REQUEST_RANDOM: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+/// Bytes of the server's first record up to the end of the ServerHello random:
+/// record header, handshake header and legacy version.
+const SERVER_HELLO_PREFIX: usize = 5 + 4 + 2 + 32;
+
+/// Whether the server's first record is a ServerHello carrying the
+/// HelloRetryRequest random.
+fn is_hello_retry_request(server_hello: &[u8]) -> bool {
+    server_hello.len() == SERVER_HELLO_PREFIX
+        && server_hello[0] == 0x16
+        && server_hello[5] == 0x02
+        && server_hello[11..] == HELLO_RETRY_REQUEST_RANDOM
+}
+
+#[derive(Debug)]
+enum Flight {
+    Client,
+    Server,
+    Done,
+}
+
+/// Stream wrapper counting the bytes and calls going through it.
+#[derive(Debug)]
+pub struct CountingStream<S> {
+    inner: S,
+    stats: WireStats,
+    flight: Flight,
+    server_hello: Vec<u8>,
+    hello_retry_request: bool,
+    client_hello_sent: Option<Instant>,
+    first_server_byte: Option<Instant>,
+    server_flight_received: Option<Instant>,
+    client_finished_sent: Option<Instant>,
+}
+
+impl<S> CountingStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            stats: WireStats::default(),
+            flight: Flight::Client,
+            server_hello: Vec::with_capacity(SERVER_HELLO_PREFIX),
+            hello_retry_request: false,
+            client_hello_sent: None,
+            first_server_byte: None,
+            server_flight_received: None,
+            client_finished_sent: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> WireStats {
+        self.stats
+    }
+
+    /// Whether the server answered the first ClientHello with a
+    /// HelloRetryRequest, for handshakers that don't tell.
+    #[cfg(feature = "openssl")]
+    pub fn hello_retry_request(&self) -> bool {
+        self.hello_retry_request
+    }
+
+    /// Phases of a handshake that completed at `done`.
+    pub fn phases(&self, done: Instant) -> HandshakePhases {
+        let between = |from: Option<Instant>, to: Option<Instant>| {
+            from.zip(to)
+                .map(|(from, to)| to.saturating_duration_since(from))
+        };
+        HandshakePhases {
+            time_to_first_byte: between(self.client_hello_sent, self.first_server_byte),
+            server_flight: between(self.first_server_byte, self.server_flight_received),
+            finished_to_done: between(self.client_finished_sent, Some(done)),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CountingStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), Error>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - filled;
+        if read > 0 {
+            self.stats.bytes_received += read;
+            self.stats.reads += 1;
+            let now = Instant::now();
+            if let Flight::Client = self.flight {
+                self.flight = Flight::Server;
+                self.first_server_byte = Some(now);
+            }
+            if let Flight::Server = self.flight {
+                self.stats.server_flight += read;
+                self.server_flight_received = Some(now);
+                let missing = SERVER_HELLO_PREFIX - self.server_hello.len();
+                let received = &buf.filled()[filled..filled + read.min(missing)];
+                self.server_hello.extend_from_slice(received);
+            }
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CountingStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            if written > 0 {
+                self.stats.bytes_sent += written;
+                self.stats.writes += 1;
+                match self.flight {
+                    Flight::Client => {
+                        self.stats.client_hello += written;
+                        self.client_hello_sent = Some(Instant::now());
+                    }
+                    // The retried ClientHello starts the handshake over.
+                    Flight::Server
+                        if !self.hello_retry_request
+                            && is_hello_retry_request(&self.server_hello) =>
+                    {
+                        self.hello_retry_request = true;
+                        self.flight = Flight::Client;
+                        self.stats.client_hello = written;
+                        self.stats.server_flight = 0;
+                        self.client_hello_sent = Some(Instant::now());
+                        self.first_server_byte = None;
+                        self.server_flight_received = None;
+                    }
+                    Flight::Server => {
+                        self.flight = Flight::Done;
+                        self.client_finished_sent = Some(Instant::now());
+                    }
+                    Flight::Done => {}
+                }
+            }
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_counting_stream_flights() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut stream = CountingStream::new(client);
+        let mut buffer = [0; 16];
+        let pause = Duration::from_millis(5);
+        let start = Instant::now();
+
+        stream.write_all(b"hello").await.unwrap();
+        stream.write_all(b"!").await.unwrap();
+        server.read_exact(&mut buffer[..6]).await.unwrap();
+        assert_eq!(&buffer[..6], b"hello!");
+        tokio::time::sleep(pause).await;
+        server.write_all(b"ser").await.unwrap();
+        stream.read_exact(&mut buffer[..3]).await.unwrap();
+        tokio::time::sleep(pause).await;
+        server.write_all(b"ver").await.unwrap();
+        stream.read_exact(&mut buffer[..3]).await.unwrap();
+        stream.write_all(b"finished").await.unwrap();
+        server.write_all(b"ticket").await.unwrap();
+        stream.read_exact(&mut buffer[..6]).await.unwrap();
+        tokio::time::sleep(pause).await;
+        let done = Instant::now();
+
+        let stats = stream.stats();
+        assert_eq!(stats.bytes_sent, "hello!finished".len());
+        assert_eq!(stats.bytes_received, "serverticket".len());
+        assert_eq!(stats.writes, 3);
+        assert_eq!(stats.reads, 3);
+        assert_eq!(stats.client_hello, "hello!".len());
+        assert_eq!(stats.server_flight, "server".len());
+
+        assert!(stream.client_hello_sent <= stream.first_server_byte);
+        assert!(stream.first_server_byte <= stream.server_flight_received);
+        assert!(stream.server_flight_received <= stream.client_finished_sent);
+
+        let phases = stream.phases(done);
+        let time_to_first_byte = phases.time_to_first_byte.unwrap();
+        let server_flight = phases.server_flight.unwrap();
+        let finished_to_done = phases.finished_to_done.unwrap();
+        assert!(time_to_first_byte >= pause);
+        assert!(server_flight >= pause);
+        assert!(finished_to_done >= pause);
+        assert!(time_to_first_byte + server_flight + finished_to_done <= done - start);
+        assert!(!stream.hello_retry_request);
+    }
+
+    /// Record with a HelloRetryRequest, followed by the rest of its extensions.
+    fn hello_retry_request_record() -> Vec<u8> {
+        let mut record = vec![
+            0x16, 0x03, 0x03, 0x00, 0x2e, 0x02, 0x00, 0x00, 0x2a, 0x03, 0x03,
+        ];
+        record.extend_from_slice(&HELLO_RETRY_REQUEST_RANDOM);
+        record.extend_from_slice(b"extensions");
+        record
+    }
+
+    #[tokio::test]
+    async fn test_counting_stream_hello_retry_request() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut stream = CountingStream::new(client);
+        let mut buffer = [0; 64];
+        let pause = Duration::from_millis(5);
+        let hello_retry_request = hello_retry_request_record();
+
+        stream.write_all(b"hello1").await.unwrap();
+        // The HelloRetryRequest random spans two reads.
+        server.write_all(&hello_retry_request[..20]).await.unwrap();
+        stream.read_exact(&mut buffer[..20]).await.unwrap();
+        server.write_all(&hello_retry_request[20..]).await.unwrap();
+        let rest = hello_retry_request.len() - 20;
+        stream.read_exact(&mut buffer[..rest]).await.unwrap();
+        tokio::time::sleep(pause).await;
+
+        let retry = Instant::now();
+        stream.write_all(b"hello2").await.unwrap();
+        tokio::time::sleep(pause).await;
+        server.write_all(b"server").await.unwrap();
+        stream.read_exact(&mut buffer[..6]).await.unwrap();
+        stream.write_all(b"finished").await.unwrap();
+        tokio::time::sleep(pause).await;
+        let done = Instant::now();
+
+        assert!(stream.hello_retry_request);
+        let stats = stream.stats();
+        assert_eq!(stats.client_hello, "hello2".len());
+        assert_eq!(stats.server_flight, "server".len());
+        let hello_retry_round_trip = "hello1".len() + hello_retry_request.len();
+        assert_eq!(
+            stats.bytes_sent + stats.bytes_received,
+            hello_retry_round_trip + "hello2serverfinished".len()
+        );
+        let phases = stream.phases(done);
+        let time_to_first_byte = phases.time_to_first_byte.unwrap();
+        let server_flight = phases.server_flight.unwrap();
+        let finished_to_done = phases.finished_to_done.unwrap();
+        assert!(time_to_first_byte >= pause);
+        assert!(finished_to_done >= pause);
+        assert!(time_to_first_byte + server_flight + finished_to_done <= done - retry);
+    }
+
+    #[test]
+    fn test_phases_without_server_answer() {
+        let (client, _server) = tokio::io::duplex(1024);
+        let stream = CountingStream::new(client);
+        assert_eq!(stream.phases(Instant::now()), HandshakePhases::default());
+    }
+}